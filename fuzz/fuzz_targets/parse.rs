@@ -0,0 +1,13 @@
+#![no_main]
+
+use idl_parser::EndpointParser;
+use libfuzzer_sys::fuzz_target;
+
+// Any input must come back as `Ok` or `Err`, never as a panic.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = EndpointParser::parse_endpoint(input);
+        let _ = EndpointParser::parse_document(input);
+        let _ = EndpointParser::parse_document_recovering(input);
+    }
+});
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
+
+use crate::{literal::unescape, Children, Document, ParseError, Rule, Spanned};
+
+/// `@timeout(5s)` in front of an endpoint. Arguments are kept as written,
+/// with the quotes of string arguments removed.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Annotation {
+    pub name: String,
+    pub args: Vec<Spanned<String>>,
+}
+
+impl Annotation {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into().into());
+        self
+    }
+}
+
+/// Checks the arguments of an annotation, returning what is wrong with them.
+pub type Validator = Box<dyn Fn(&Annotation) -> Result<(), String>>;
+
+/// The annotations a document may use and how to check their arguments.
+///
+/// ```
+/// # use idl_parser::{AnnotationRegistry, EndpointParser};
+/// let registry = AnnotationRegistry::builtin().register("owner", |v| match v.args.len() {
+///     1 => Ok(()),
+///     _ => Err("expected a team name".to_owned()),
+/// });
+/// let document = EndpointParser::parse_document("@owner(billing)\nGET /invoices")?;
+/// assert!(registry.validate(&document).is_ok());
+/// # Ok::<_, idl_parser::ParseError>(())
+/// ```
+#[derive(Default)]
+pub struct AnnotationRegistry {
+    validators: HashMap<String, Validator>,
+}
+
+impl AnnotationRegistry {
+    /// Schemes `@auth` accepts.
+    pub const AUTH_SCHEMES: &'static [&'static str] =
+        &["none", "basic", "bearer", "api-key", "oauth2"];
+
+    /// A registry that knows no annotations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry that knows `@deprecated`, `@internal`, `@tag`, `@auth`
+    /// and `@timeout`.
+    pub fn builtin() -> Self {
+        Self::new()
+            .register("deprecated", |v| match v.args.len() {
+                0 | 1 => Ok(()),
+                _ => Err("expected at most a message".to_owned()),
+            })
+            .register("internal", |v| match v.args.len() {
+                0 => Ok(()),
+                _ => Err("expected no arguments".to_owned()),
+            })
+            .register("tag", |v| match v.args.len() {
+                0 => Err("expected at least one tag".to_owned()),
+                _ => Ok(()),
+            })
+            .register("auth", |v| match v.args.as_slice() {
+                [scheme] if Self::AUTH_SCHEMES.contains(&scheme.as_str()) => Ok(()),
+                _ => Err(format!("expected one of {}", Self::AUTH_SCHEMES.join(", "))),
+            })
+            .register("timeout", |v| match v.args.as_slice() {
+                [timeout] if is_timeout(timeout) => Ok(()),
+                _ => Err("expected a timeout like 500ms, 5s, 2m or 1h".to_owned()),
+            })
+    }
+
+    /// Adds or replaces the annotation `name`.
+    pub fn register(
+        mut self,
+        name: impl Into<String>,
+        validator: impl Fn(&Annotation) -> Result<(), String> + 'static,
+    ) -> Self {
+        self.validators.insert(name.into(), Box::new(validator));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.validators.contains_key(name)
+    }
+
+    /// Checks every annotation of `document` is registered and accepts its
+    /// arguments.
+    pub fn validate(&self, document: &Document) -> Result<(), Vec<ParseError>> {
+        let errors: Vec<_> = document
+            .all_endpoints()
+            .flat_map(|v| &v.annotations)
+            .filter_map(|v| self.check(v).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check(&self, annotation: &Spanned<Annotation>) -> Result<(), ParseError> {
+        let Some(validator) = self.validators.get(&annotation.name) else {
+            return Err(ParseError::UnknownAnnotation {
+                name: annotation.name.clone(),
+                span: annotation.span,
+            });
+        };
+        validator(annotation).map_err(|reason| ParseError::InvalidAnnotation {
+            name: annotation.name.clone(),
+            reason,
+            span: annotation.span,
+        })
+    }
+}
+
+fn is_timeout(text: &str) -> bool {
+    let unit = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    unit.len() < text.len() && ["ms", "s", "m", "h"].contains(&unit)
+}
+
+impl TryFrom<Pair<'_, Rule>> for Annotation {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::annotation == value.as_rule() {
+            let mut pairs = Children::of(value);
+            let name = pairs.next_pair()?;
+            let args = pairs
+                .map(|v| {
+                    let span = v.as_span().into();
+                    let arg = Children::of(v).next_pair()?;
+                    let text = match arg.as_rule() {
+                        Rule::string_literal => unescape(arg.as_str()),
+                        _ => arg.as_str().to_owned(),
+                    };
+                    Ok(Spanned::new(text, span))
+                })
+                .collect::<Result<_, ParseError>>()?;
+
+            Ok(Self {
+                name: name.as_str().to_owned(),
+                args,
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::annotation, &value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_annotations() {
+        let registry = AnnotationRegistry::builtin();
+        let valid = [
+            Annotation::new("deprecated"),
+            Annotation::new("deprecated").arg("use /v2"),
+            Annotation::new("internal"),
+            Annotation::new("tag").arg("billing").arg("public"),
+            Annotation::new("auth").arg("bearer"),
+            Annotation::new("timeout").arg("500ms"),
+        ];
+        for annotation in valid {
+            assert!(
+                registry.check(&annotation.clone().into()).is_ok(),
+                "{annotation:?}"
+            );
+        }
+
+        let invalid = [
+            Annotation::new("internal").arg("yes"),
+            Annotation::new("tag"),
+            Annotation::new("auth").arg("magic"),
+            Annotation::new("timeout").arg("5"),
+            Annotation::new("timeout").arg("s"),
+        ];
+        for annotation in invalid {
+            let err = registry.check(&annotation.clone().into());
+            assert!(
+                matches!(err, Err(ParseError::InvalidAnnotation { .. })),
+                "{annotation:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::cmp::Ordering;
+
+use pest::iterators::Pair;
+use regex::Regex;
+
+use crate::{literal::unescape, Children, Literal, ParseError, Rule, Span, VariableType};
+
+/// Limits on the values of a variable beyond its type, e.g.
+/// `size:int(1..=100)` or `code:string(len=3, pattern="[A-Z]+")`.
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct Constraints {
+    /// Bounds of a numeric value.
+    pub range: Option<Range<Literal>>,
+    /// Bounds of the length of a textual value, in characters. `len=3` is
+    /// stored as `3..=3`.
+    pub length: Option<Range<u64>>,
+    pub pattern: Option<Pattern>,
+}
+
+/// `min..=max` or `min..max`, either end may be left out.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Range<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+    /// `min..max` rather than `min..=max`.
+    pub exclusive_max: bool,
+}
+
+/// A regular expression a textual value must match as a whole.
+///
+/// Exporters to formats where patterns match anywhere in the value, such as
+/// OpenAPI, have to anchor [`Pattern::as_str`] themselves.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.range.is_none() && self.length.is_none() && self.pattern.is_none()
+    }
+
+    /// Whether `value`, already of the variable's type, meets every
+    /// constraint.
+    pub fn admits(&self, value: &Literal) -> bool {
+        let text = match value {
+            Literal::String(text) => Some(text.as_str()),
+            _ => None,
+        };
+        self.range.as_ref().is_none_or(|v| v.contains(value))
+            && text.is_none_or(|text| {
+                let len = text.chars().count() as u64;
+                self.length.as_ref().is_none_or(|v| v.contains(&len))
+                    && self.pattern.as_ref().is_none_or(|v| v.is_match(text))
+            })
+    }
+
+    /// Converts a `constraints` pair, checking they apply to `variable_type`.
+    pub(crate) fn of_type(
+        value: Pair<'_, Rule>,
+        variable_type: &VariableType,
+    ) -> Result<Self, ParseError> {
+        if Rule::constraints != value.as_rule() {
+            return Err(ParseError::unexpect_rule(Rule::constraints, &value));
+        }
+        let mut constraints = Self::default();
+        for pair in value.into_inner() {
+            let invalid = invalid(&pair);
+            let rule = pair.as_rule();
+            let given = match rule {
+                Rule::range => constraints.range.is_some(),
+                Rule::length => constraints.length.is_some(),
+                _ => constraints.pattern.is_some(),
+            };
+            if given {
+                return Err(invalid("given twice".to_owned()));
+            }
+            let applies = match rule {
+                Rule::range => variable_type.is_numeric(),
+                _ => variable_type.is_textual(),
+            };
+            if !applies {
+                return Err(invalid(format!(
+                    "it does not apply to {}",
+                    variable_type.as_str()
+                )));
+            }
+
+            match rule {
+                Rule::range => {
+                    let bound = |v: &str| {
+                        variable_type
+                            .parse_literal(v)
+                            .ok_or_else(|| bound_error(v, variable_type))
+                    };
+                    constraints.range = Some(range(pair, bound, compare_numbers)?)
+                }
+                Rule::length => {
+                    let inner = Children::of(pair).next_pair()?;
+                    constraints.length = Some(match inner.as_rule() {
+                        Rule::range => range(inner, length_bound, u64::partial_cmp)?,
+                        _ => {
+                            let len = inner.as_str().parse().ok();
+                            let len = len.ok_or_else(|| invalid("not a length".to_owned()))?;
+                            Range {
+                                min: Some(len),
+                                max: Some(len),
+                                exclusive_max: false,
+                            }
+                        }
+                    });
+                }
+                _ => {
+                    let source = unescape(Children::of(pair).next_pair()?.as_str());
+                    let pattern = Pattern::new(source).map_err(|v| invalid(v.to_string()))?;
+                    constraints.pattern = Some(pattern);
+                }
+            }
+        }
+        Ok(constraints)
+    }
+}
+
+impl Range<u64> {
+    pub fn contains(&self, value: &u64) -> bool {
+        self.contains_by(value, u64::partial_cmp)
+    }
+}
+
+impl Range<Literal> {
+    pub fn contains(&self, value: &Literal) -> bool {
+        self.contains_by(value, compare_numbers)
+    }
+}
+
+impl<T> Range<T> {
+    fn contains_by(&self, value: &T, cmp: impl Fn(&T, &T) -> Option<Ordering>) -> bool {
+        let above_min = self
+            .min
+            .as_ref()
+            .is_none_or(|min| cmp(value, min).is_some_and(Ordering::is_ge));
+        let below_max = self.max.as_ref().is_none_or(|max| match cmp(value, max) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => !self.exclusive_max,
+            _ => false,
+        });
+        above_min && below_max
+    }
+
+    fn is_empty_by(&self, cmp: impl Fn(&T, &T) -> Option<Ordering>) -> bool {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) => match cmp(min, max) {
+                Some(Ordering::Less) => false,
+                Some(Ordering::Equal) => self.exclusive_max,
+                _ => true,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(source: impl Into<String>) -> Result<Self, regex::Error> {
+        let source = source.into();
+        let regex = Regex::new(&format!("^(?:{source})$"))?;
+        Ok(Self { source, regex })
+    }
+
+    /// The pattern as written in the IDL.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl PartialOrd for Pattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.source.partial_cmp(&other.source)
+    }
+}
+
+/// Converts a `range` pair, parsing its bounds with `bound`, which says what
+/// is wrong with a bound it rejects.
+fn range<T>(
+    value: Pair<'_, Rule>,
+    bound: impl Fn(&str) -> Result<T, String>,
+    cmp: impl Fn(&T, &T) -> Option<Ordering>,
+) -> Result<Range<T>, ParseError> {
+    let invalid = invalid(&value);
+    let mut range = Range {
+        min: None,
+        max: None,
+        exclusive_max: false,
+    };
+    let mut after_op = false;
+    for pair in value.into_inner() {
+        if pair.as_rule() == Rule::range_op {
+            range.exclusive_max = pair.as_str() == "..";
+            after_op = true;
+            continue;
+        }
+        let value = bound(pair.as_str()).map_err(&invalid)?;
+        if after_op {
+            range.max = Some(value);
+        } else {
+            range.min = Some(value);
+        }
+    }
+
+    if range.min.is_none() && range.max.is_none() {
+        return Err(invalid("a range needs a bound".to_owned()));
+    }
+    if range.is_empty_by(cmp) {
+        return Err(invalid("the range is empty".to_owned()));
+    }
+    Ok(range)
+}
+
+/// Why `text`, a `bound`, is not a value of `variable_type`.
+fn bound_error(text: &str, variable_type: &VariableType) -> String {
+    if variable_type.integer_range().is_some() && text.contains('.') {
+        format!("`{text}` is not a valid {}", variable_type.as_str())
+    } else {
+        format!("`{text}` is out of range")
+    }
+}
+
+fn length_bound(text: &str) -> Result<u64, String> {
+    text.parse().map_err(|_| {
+        if text.bytes().all(|v| v.is_ascii_digit()) {
+            format!("`{text}` is out of range")
+        } else {
+            format!("`{text}` is not a length")
+        }
+    })
+}
+
+fn invalid(pair: &Pair<'_, Rule>) -> impl Fn(String) -> ParseError {
+    let constraint = pair.as_str().to_owned();
+    let span: Span = pair.as_span().into();
+    move |reason| ParseError::InvalidConstraint {
+        constraint: constraint.clone(),
+        reason,
+        span,
+    }
+}
+
+fn compare_numbers(a: &Literal, b: &Literal) -> Option<Ordering> {
+    let as_f64 = |v: &Literal| match v {
+        Literal::Integer(v) => Some(*v as f64),
+        Literal::Float(v) => Some(*v),
+        Literal::Decimal(v) => v.parse().ok(),
+        _ => None,
+    };
+    match (a, b) {
+        (Literal::Integer(a), Literal::Integer(b)) => Some(a.cmp(b)),
+        _ => as_f64(a)?.partial_cmp(&as_f64(b)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admits() {
+        let constraints = Constraints {
+            range: Some(Range {
+                min: Some(Literal::Integer(1)),
+                max: Some(Literal::Integer(100)),
+                exclusive_max: true,
+            }),
+            ..Default::default()
+        };
+        assert!(constraints.admits(&Literal::Integer(1)));
+        assert!(!constraints.admits(&Literal::Integer(100)));
+        assert!(!constraints.admits(&Literal::Integer(0)));
+
+        let constraints = Constraints {
+            length: Some(Range {
+                min: Some(2),
+                max: None,
+                exclusive_max: false,
+            }),
+            pattern: Some(Pattern::new("[A-Z]+").unwrap()),
+            ..Default::default()
+        };
+        assert!(constraints.admits(&Literal::String("EUR".to_owned())));
+        assert!(!constraints.admits(&Literal::String("E".to_owned())));
+        assert!(!constraints.admits(&Literal::String("EUR1".to_owned())));
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::Write;
+
+use pest::{
+    error::{ErrorVariant, InputLocation, LineColLocation},
+    iterators::Pair,
+};
+
+use crate::{MediaType, Rule, Span, VariableType};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseError {
+    #[error("unexpected {found:?} at {span}, expected {expected:?}")]
+    UnexpectRule {
+        expected: Rule,
+        found: Rule,
+        span: Span,
+    },
+    #[error(
+        "unknown type `{name}` at {span}, expected one of {}",
+        VariableType::NAMES.join(", ")
+    )]
+    UnsupportType { name: String, span: Span },
+    #[error(
+        "unknown media type `{name}` at {span}, expected one of {} or a `type/subtype`",
+        MediaType::NAMES.join(", ")
+    )]
+    UnsupportMediaType { name: String, span: Span },
+    #[error("invalid default `{value}` at {span}, expected a value of type {expected}")]
+    InvalidDefault {
+        value: String,
+        expected: String,
+        span: Span,
+    },
+    #[error("invalid constraint `{constraint}` at {span}, {reason}")]
+    InvalidConstraint {
+        constraint: String,
+        reason: String,
+        span: Span,
+    },
+    #[error("invalid catch-all `{name}` at {span}, {reason}")]
+    InvalidCatchAll {
+        name: String,
+        reason: String,
+        span: Span,
+    },
+    #[error("`{name}` at {span} is a built-in type, a declaration cannot take its name")]
+    ReservedName { name: String, span: Span },
+    #[error("undeclared type `{name}` at {span}")]
+    UndeclaredType { name: String, span: Span },
+    #[error("`{name}` at {span} is not an enum, parameters need a scalar or enum type")]
+    NotAnEnum { name: String, span: Span },
+    #[error("`{name}` at {span} takes {expected} type argument(s) but {found} were given")]
+    TypeArity {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("unknown annotation `@{name}` at {span}")]
+    UnknownAnnotation { name: String, span: Span },
+    #[error("invalid arguments for `@{name}` at {span}, {reason}")]
+    InvalidAnnotation {
+        name: String,
+        reason: String,
+        span: Span,
+    },
+    #[error("cannot import `{path}` at {span}: {reason}")]
+    Import {
+        path: String,
+        reason: String,
+        span: Span,
+    },
+    #[error("`{path}` at {span} imports itself through {}", .cycle.join(" -> "))]
+    ImportCycle {
+        path: String,
+        cycle: Vec<String>,
+        span: Span,
+    },
+    #[error("duplicate definition of `{name}` at {span}")]
+    Duplicate { name: String, span: Span },
+    #[error("unexpected end of {rule:?} at {span}")]
+    UnexpectEnd { rule: Rule, span: Span },
+    #[error("unexpected {} at {span}, expected {}", describe_found(.found), one_of(.expected))]
+    Syntax {
+        found: String,
+        expected: Vec<String>,
+        span: Span,
+    },
+}
+
+impl ParseError {
+    pub(crate) fn unexpect_rule(expected: Rule, found: &Pair<'_, Rule>) -> Self {
+        Self::UnexpectRule {
+            expected,
+            found: found.as_rule(),
+            span: found.as_span().into(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnexpectRule { span, .. }
+            | Self::UnsupportType { span, .. }
+            | Self::UnsupportMediaType { span, .. }
+            | Self::InvalidDefault { span, .. }
+            | Self::InvalidConstraint { span, .. }
+            | Self::InvalidCatchAll { span, .. }
+            | Self::ReservedName { span, .. }
+            | Self::UndeclaredType { span, .. }
+            | Self::NotAnEnum { span, .. }
+            | Self::TypeArity { span, .. }
+            | Self::UnknownAnnotation { span, .. }
+            | Self::InvalidAnnotation { span, .. }
+            | Self::Import { span, .. }
+            | Self::ImportCycle { span, .. }
+            | Self::Duplicate { span, .. }
+            | Self::UnexpectEnd { span, .. }
+            | Self::Syntax { span, .. } => *span,
+        }
+    }
+
+    /// Rebases an error found in `source[by..]` onto `source`.
+    pub(crate) fn offset(mut self, source: &str, by: usize) -> Self {
+        match &mut self {
+            Self::UnexpectRule { span, .. }
+            | Self::UnsupportType { span, .. }
+            | Self::UnsupportMediaType { span, .. }
+            | Self::InvalidDefault { span, .. }
+            | Self::InvalidConstraint { span, .. }
+            | Self::InvalidCatchAll { span, .. }
+            | Self::ReservedName { span, .. }
+            | Self::UndeclaredType { span, .. }
+            | Self::NotAnEnum { span, .. }
+            | Self::TypeArity { span, .. }
+            | Self::UnknownAnnotation { span, .. }
+            | Self::InvalidAnnotation { span, .. }
+            | Self::Import { span, .. }
+            | Self::ImportCycle { span, .. }
+            | Self::Duplicate { span, .. }
+            | Self::UnexpectEnd { span, .. }
+            | Self::Syntax { span, .. } => *span = span.offset(source, by),
+        }
+        self
+    }
+
+    /// Renders the error like a compiler diagnostic: the message followed by
+    /// the offending line of `source` with the span underlined.
+    ///
+    /// ```text
+    /// error: unknown type `strng` at 1:12, expected one of string, ...
+    ///   |
+    /// 1 | GET /a/{id:strng}
+    ///   |            ^^^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("error: {self}\n");
+        let span = self.span();
+        let Some(line) = span.line.checked_sub(1).and_then(|v| source.lines().nth(v)) else {
+            return out;
+        };
+
+        let gutter = " ".repeat(span.line.to_string().len());
+        let prefix: String = line
+            .chars()
+            .take(span.col.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source
+            .get(span.start..span.end)
+            .and_then(|v| v.lines().next())
+            .map_or(0, |v| v.chars().count())
+            .max(1);
+
+        let _ = writeln!(out, "{gutter} |");
+        let _ = writeln!(out, "{} | {line}", span.line);
+        let _ = writeln!(out, "{gutter} | {prefix}{}", "^".repeat(width));
+        out
+    }
+}
+
+impl From<pest::error::Error<Rule>> for ParseError {
+    fn from(value: pest::error::Error<Rule>) -> Self {
+        let (line, col) = match value.line_col {
+            LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+        };
+        let found = found_token(value.line().chars().skip(col.saturating_sub(1)));
+        let start = match value.location {
+            InputLocation::Pos(pos) | InputLocation::Span((pos, _)) => pos,
+        };
+        let span = Span {
+            start,
+            end: start + found.len(),
+            line,
+            col,
+        };
+
+        let expected = match &value.variant {
+            ErrorVariant::ParsingError { positives, .. } => positives,
+            ErrorVariant::CustomError { message } => {
+                return Self::Syntax {
+                    found,
+                    expected: vec![message.clone()],
+                    span,
+                }
+            }
+        };
+
+        if expected == &[Rule::variable_type] && !found.is_empty() {
+            return Self::UnsupportType { name: found, span };
+        }
+
+        let mut tokens: Vec<String> = Vec::new();
+        for token in expected.iter().flat_map(|v| describe_rule(*v)) {
+            if !tokens.contains(&token) {
+                tokens.push(token);
+            }
+        }
+        Self::Syntax {
+            found,
+            expected: tokens,
+            span,
+        }
+    }
+}
+
+/// The token starting at the error position: a whole word, or a single
+/// character for punctuation.
+fn found_token(mut chars: impl Iterator<Item = char>) -> String {
+    match chars.next() {
+        Some(c) if c.is_alphanumeric() || c == '_' => std::iter::once(c)
+            .chain(chars.take_while(|c| c.is_alphanumeric() || *c == '_'))
+            .collect(),
+        Some(c) if c != '\r' && c != '\n' => c.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// What the user should have written in place of `rule`.
+fn describe_rule(rule: Rule) -> Vec<String> {
+    let tokens: &[&str] = match rule {
+        Rule::method | Rule::extension_method => &[
+            "GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "TRACE", "CONNECT",
+        ],
+        Rule::get => &["GET"],
+        Rule::post => &["POST"],
+        Rule::put => &["PUT"],
+        Rule::delete => &["DELETE"],
+        Rule::patch => &["PATCH"],
+        Rule::head => &["HEAD"],
+        Rule::options => &["OPTIONS"],
+        Rule::trace => &["TRACE"],
+        Rule::connect => &["CONNECT"],
+        Rule::variable_type => VariableType::NAMES,
+        Rule::path | Rule::segment | Rule::trailing_slash => &["`/`"],
+        Rule::segment_text => &["path segment"],
+        Rule::path_variable | Rule::mixed_segment => &["`/{`"],
+        Rule::catch_all => &["`/{*`"],
+        Rule::query_params => &["`?`"],
+        Rule::name => &["name"],
+        Rule::variable | Rule::parameter => &["variable"],
+        Rule::annotation => &["`@`"],
+        Rule::annotation_name => &["annotation"],
+        Rule::headers | Rule::response_headers => &["`[`"],
+        Rule::header | Rule::header_name => &["header"],
+        Rule::optional => &["`?`"],
+        Rule::list_type => &["`[`"],
+        Rule::list_style | Rule::repeat | Rule::csv => &["repeat", "csv"],
+        Rule::literal | Rule::string_literal | Rule::bare_literal => &["value"],
+        Rule::constraints => &["`(`"],
+        Rule::length => &["len"],
+        Rule::pattern => &["pattern"],
+        Rule::range | Rule::range_op | Rule::bound => &["range"],
+        Rule::request_type => &["request type"],
+        Rule::response_type => &["response type"],
+        Rule::status_response | Rule::status => &["status code"],
+        Rule::media_type => MediaType::NAMES,
+        Rule::endpoint => &["endpoint"],
+        Rule::document => &["endpoint or declaration"],
+        Rule::type_def => &["type declaration"],
+        Rule::enum_def => &["enum declaration"],
+        Rule::service => &["service"],
+        Rule::import => &["import"],
+        Rule::keyword => &["import", "type declaration", "enum declaration", "service"],
+        Rule::enum_value => &["enum value"],
+        Rule::enum_ref => &["enum"],
+        Rule::field => &["field"],
+        Rule::type_expr => &["type"],
+        Rule::type_params => &["`<`"],
+        Rule::doc_comment => &["doc comment"],
+        Rule::EOI => &["end of input"],
+        _ => return vec![format!("{rule:?}")],
+    };
+    tokens.iter().map(|v| v.to_string()).collect()
+}
+
+fn describe_found(found: &str) -> String {
+    if found.is_empty() {
+        "end of input".to_owned()
+    } else {
+        format!("`{found}`")
+    }
+}
+
+fn one_of(expected: &[String]) -> String {
+    match expected {
+        [] => "nothing".to_owned(),
+        [token] => token.clone(),
+        tokens => format!("one of {}", tokens.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EndpointParser;
+
+    use super::*;
+
+    #[test]
+    fn test_unknown_type() {
+        let err = EndpointParser::parse_document("GET /a/{id:strng}").unwrap_err();
+        assert!(matches!(&err, ParseError::UnsupportType { name, .. } if name == "strng"));
+        assert_eq!(
+            format!(
+                "unknown type `strng` at 1:12, expected one of {}",
+                VariableType::NAMES.join(", ")
+            ),
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_in_endpoint() {
+        for (input, name) in [("GET /a/{id:strng}", "strng"), ("GET /a?id:itn", "itn")] {
+            let err = EndpointParser::parse_endpoint(input).unwrap_err();
+            assert!(
+                matches!(&err, ParseError::UnsupportType { name: found, .. } if found == name),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let err = EndpointParser::parse_document("GET /a RQ -> RS junk").unwrap_err();
+        let ParseError::Syntax {
+            found,
+            expected,
+            span,
+        } = &err
+        else {
+            panic!("unexpected error {err:?}")
+        };
+        assert_eq!("junk", found);
+        assert!(expected.contains(&"end of input".to_owned()));
+        assert_eq!((16, 20, 1, 17), (span.start, span.end, span.line, span.col));
+
+        // an endpoint is the whole input, not a prefix of it
+        let err = EndpointParser::parse_endpoint("GET /a RQ -> RS junk").unwrap_err();
+        assert!(
+            matches!(&err, ParseError::Syntax { found, .. } if found == "junk"),
+            "{err:?}"
+        );
+        let err = EndpointParser::parse_endpoint("GET /a\nGET /b").unwrap_err();
+        assert_eq!(2, err.span().line);
+
+        let err = EndpointParser::parse_document("purge /a").unwrap_err();
+        assert_eq!(
+            "unexpected `purge` at 1:1, expected endpoint or declaration",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "GET /a\n  GET /a/{id:strng}\n";
+        let err = EndpointParser::parse_document(source).unwrap_err();
+        assert_eq!(
+            format!(
+                "error: {err}
+  |
+2 |   GET /a/{{id:strng}}
+  |              ^^^^^
+"
+            ),
+            err.render(source)
+        );
+        assert!(err.to_string().starts_with("unknown type `strng` at 2:14"));
+    }
+}
@@ -1,6 +1,35 @@
-use pest::{iterators::Pair, Parser};
+use pest::{
+    iterators::{Pair, Pairs},
+    Parser,
+};
 use pest_derive::Parser;
 
+mod annotation;
+mod constraint;
+mod error;
+mod literal;
+mod loader;
+mod media;
+mod printer;
+mod resolve;
+mod service;
+mod source_map;
+mod span;
+mod types;
+
+pub use annotation::{Annotation, AnnotationRegistry, Validator};
+pub use constraint::{Constraints, Pattern, Range};
+pub use error::ParseError;
+pub use literal::Literal;
+pub use loader::{
+    FileError, FileSystem, LoadError, Loader, MemoryFileSystem, Module, OsFileSystem, Project,
+};
+pub use media::MediaType;
+pub use service::Service;
+pub use source_map::{FileId, SourceFile, SourceMap};
+pub use span::{Span, Spanned};
+pub use types::{EnumDef, Field, TypeDef, TypeExpr};
+
 #[derive(Parser)]
 #[grammar = "grammar.pest"] // relative to src
 pub struct EndpointParser;
@@ -8,27 +37,138 @@ pub struct EndpointParser;
 type TypeName = String;
 
 impl EndpointParser {
+    /// Parses an input holding a single endpoint and nothing else. With no
+    /// declarations to look enum names up in, variables must have scalar
+    /// types.
     pub fn parse_endpoint(input: &str) -> Result<Endpoint, ParseError> {
-        let value = Self::parse(Rule::endpoint, input)
-            .map_err(Box::new)?
-            .next()
-            .unwrap();
+        let endpoint: Endpoint = Self::parse_rule(Rule::single_endpoint, input)?;
+        let variables = endpoint
+            .path
+            .iter()
+            .flat_map(|v| v.variables())
+            .chain(endpoint.query_params.iter().map(|v| &v.node))
+            .chain(endpoint.headers.iter().map(|v| &v.node))
+            .chain(endpoint.response_headers.iter().map(|v| &v.node));
+        for variable in variables {
+            if let VariableType::Enum(name) = &variable.variable_type.node {
+                return Err(ParseError::UnsupportType {
+                    name: name.clone(),
+                    span: variable.variable_type.span,
+                });
+            }
+        }
+        Ok(endpoint)
+    }
 
-        match value.as_rule() {
-            Rule::endpoint => value.try_into(),
-            _ => panic!("unreachable"),
+    /// Parses a document and resolves the type names it uses. Of several
+    /// errors, the first in the source is returned.
+    pub fn parse_document(input: &str) -> Result<Document, ParseError> {
+        let document: Document = Self::parse_rule(Rule::document, input)?;
+        if let Err(mut errors) = document.resolve() {
+            errors.sort_by_key(|v| v.span().start);
+            return Err(errors.remove(0));
         }
+        Ok(document)
+    }
+
+    /// Parses a document without stopping at the first error.
+    ///
+    /// A broken item is skipped up to the next line starting an endpoint or
+    /// declaration, or in a service body up to the `}` closing it, so the
+    /// returned document holds every item that parsed.
+    /// The errors hold one entry per broken item and per name resolution
+    /// error, in source order.
+    pub fn parse_document_recovering(input: &str) -> (Document, Vec<ParseError>) {
+        let mut document = Document::default();
+        let mut errors = Vec::new();
+        let pairs = match Self::parse(Rule::recovering_document, input) {
+            Ok(pairs) => pairs,
+            Err(err) => return (document, vec![err.into()]),
+        };
+
+        for pair in pairs.flat_map(|v| v.into_inner()) {
+            match pair.as_rule() {
+                Rule::endpoint => match pair.try_into() {
+                    Ok(endpoint) => document.endpoints.push(endpoint),
+                    Err(err) => errors.push(err),
+                },
+                Rule::type_def => match pair.try_into() {
+                    Ok(type_def) => document.types.push(type_def),
+                    Err(err) => errors.push(err),
+                },
+                Rule::enum_def => match pair.try_into() {
+                    Ok(enum_def) => document.enums.push(enum_def),
+                    Err(err) => errors.push(err),
+                },
+                Rule::service => match pair.try_into() {
+                    Ok(service) => document.services.push(service),
+                    Err(err) => errors.push(err),
+                },
+                Rule::recovering_service => match Service::recover(pair, input, &mut errors) {
+                    Ok(service) => document.services.push(service),
+                    Err(err) => errors.push(err),
+                },
+                Rule::import => match import_path(pair) {
+                    Ok(import) => document.imports.push(import),
+                    Err(err) => errors.push(err),
+                },
+                Rule::invalid => errors.extend(Self::diagnose(input, pair)),
+                _ => {}
+            }
+        }
+        if let Err(unresolved) = document.resolve() {
+            errors.extend(unresolved);
+        }
+        errors.sort_by_key(|v| v.span().start);
+        (document, errors)
+    }
+
+    /// Finds out why `invalid` did not parse by parsing it on its own. Text
+    /// that parses on its own only failed because of what follows it, which
+    /// is reported where it starts.
+    pub(crate) fn diagnose(input: &str, invalid: Pair<'_, Rule>) -> Option<ParseError> {
+        let span = invalid.as_span();
+        Self::parse_rule::<Document>(Rule::document, span.as_str())
+            .err()
+            .map(|v| v.offset(input, span.start()))
+    }
+
+    fn parse_rule<'i, T>(rule: Rule, input: &'i str) -> Result<T, ParseError>
+    where
+        T: TryFrom<Pair<'i, Rule>, Error = ParseError>,
+    {
+        Self::parse(rule, input)?
+            .next()
+            .ok_or(ParseError::UnexpectEnd {
+                rule,
+                span: Span::default(),
+            })?
+            .try_into()
     }
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum ParseError {
-    #[error("Unexpect rule")]
-    UnexpectRule,
-    #[error("Unsupport type")]
-    UnsupportType,
-    #[error("Parse error")]
-    PestError(#[from] Box<pest::error::Error<Rule>>),
+impl TryFrom<Pair<'_, Rule>> for Document {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::document == value.as_rule() {
+            let mut document = Self::default();
+            for pair in value.into_inner() {
+                match pair.as_rule() {
+                    Rule::endpoint => document.endpoints.push(pair.try_into()?),
+                    Rule::type_def => document.types.push(pair.try_into()?),
+                    Rule::enum_def => document.enums.push(pair.try_into()?),
+                    Rule::service => document.services.push(pair.try_into()?),
+                    Rule::import => document.imports.push(import_path(pair)?),
+                    _ => {}
+                }
+            }
+
+            Ok(document)
+        } else {
+            Err(ParseError::unexpect_rule(Rule::document, &value))
+        }
+    }
 }
 
 impl TryFrom<Pair<'_, Rule>> for Endpoint {
@@ -36,85 +176,330 @@ impl TryFrom<Pair<'_, Rule>> for Endpoint {
 
     fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
         if Rule::endpoint == value.as_rule() {
-            let mut inner = value.into_inner();
-            let method: Method = inner.next().unwrap().try_into()?;
-            let path: Vec<Path> = inner
-                .next()
-                .unwrap()
-                .into_inner()
-                .map(|v| v.try_into())
-                .collect::<Result<Vec<_>, _>>()
-                .unwrap();
-            let query_params: Vec<Variable> = inner
-                .next()
-                .unwrap()
+            let mut inner = Children::of(value);
+            let mut docs = Vec::new();
+            let mut annotations = Vec::new();
+            let mut pair = inner.next_documented(&mut docs)?;
+            while pair.as_rule() == Rule::annotation {
+                annotations.push(spanned(pair)?);
+                pair = inner.next_documented(&mut docs)?;
+            }
+            let method = spanned(pair)?;
+            let path = path_of(inner.next_pair()?)?;
+            let query_params: Vec<Spanned<Variable>> = inner
+                .next_pair()?
                 .into_inner()
-                .map(|v| v.try_into())
-                .collect::<Result<Vec<_>, _>>()
-                .unwrap();
-            let mut pair = inner.next();
-            let req_type: Option<RequestType> = if let Some(Ok(rq)) = pair.as_ref().map(|v| v.try_into()) {
-                pair = inner.next();
-                Some(rq)
-            } else {
-                None
-            };
-            let res_type: Option<ResponseType> = pair.and_then(|v| v.try_into().ok());
+                .map(spanned)
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut headers = Vec::new();
+            let mut response_headers = Vec::new();
+            let mut request_type = None;
+            let mut request_media_type = None;
+            let mut response_type = None;
+            let mut response_media_type = None;
+            let mut responses: Vec<Spanned<StatusResponse>> = Vec::new();
+            for pair in inner {
+                match pair.as_rule() {
+                    Rule::headers => {
+                        headers = pair.into_inner().map(spanned).collect::<Result<_, _>>()?
+                    }
+                    Rule::response_headers => {
+                        response_headers =
+                            pair.into_inner().map(spanned).collect::<Result<_, _>>()?
+                    }
+                    Rule::request_type => {
+                        let RequestType(body, media_type) = pair.try_into()?;
+                        request_type = Some(body);
+                        request_media_type = media_type;
+                    }
+                    Rule::response_type if response_type.is_some() => {
+                        return Err(ParseError::Duplicate {
+                            name: "default response".to_owned(),
+                            span: pair.as_span().into(),
+                        })
+                    }
+                    Rule::response_type => {
+                        let ResponseType(body, media_type) = pair.try_into()?;
+                        response_type = Some(body);
+                        response_media_type = media_type;
+                    }
+                    Rule::status_response => {
+                        let response = spanned::<StatusResponse>(pair)?;
+                        if responses.iter().any(|v| v.status == response.status) {
+                            return Err(ParseError::Duplicate {
+                                name: response.status.to_string(),
+                                span: response.span,
+                            });
+                        }
+                        responses.push(response);
+                    }
+                    _ => return Err(ParseError::unexpect_rule(Rule::response_type, &pair)),
+                }
+            }
 
             Ok(Self {
+                docs: join_docs(&docs),
+                annotations,
                 method,
                 path,
                 query_params,
-                request_type: req_type.map(|v| v.0),
-                response_type: res_type.map(|v| v.0),
+                headers,
+                request_type,
+                request_media_type,
+                response_type,
+                response_media_type,
+                responses,
+                response_headers,
             })
         } else {
-            Err(ParseError::UnexpectRule)
+            Err(ParseError::unexpect_rule(Rule::endpoint, &value))
         }
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+/// A whole IDL file, endpoints and declarations are kept in source order.
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct Document {
+    /// `import "common/types.idl"`, the paths as written. See [`Loader`].
+    pub imports: Vec<Spanned<String>>,
+    pub endpoints: Vec<Endpoint>,
+    pub types: Vec<TypeDef>,
+    pub enums: Vec<EnumDef>,
+    /// `service` blocks, their endpoints are not in `endpoints`.
+    pub services: Vec<Service>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Endpoint {
-    pub method: Method,
-    pub path: Vec<Path>,
-    pub query_params: Vec<Variable>,
-    pub request_type: Option<TypeName>,
-    pub response_type: Option<TypeName>,
+    /// The `///` lines above the endpoint.
+    pub docs: Option<String>,
+    /// `@deprecated`, `@auth(bearer)`, ... in front of the method.
+    pub annotations: Vec<Spanned<Annotation>>,
+    pub method: Spanned<Method>,
+    pub path: Vec<Spanned<Path>>,
+    pub query_params: Vec<Spanned<Variable>>,
+    /// `[X-Tenant-Id:string]` after the query, sent with the request.
+    pub headers: Vec<Spanned<Variable>>,
+    pub request_type: Option<Spanned<TypeExpr>>,
+    /// `RQ as multipart`, `None` for the JSON default.
+    pub request_media_type: Option<Spanned<MediaType>>,
+    /// The default response, used for any status without its own entry.
+    pub response_type: Option<Spanned<TypeExpr>>,
+    pub response_media_type: Option<Spanned<MediaType>>,
+    /// Responses for specific statuses, in source order.
+    pub responses: Vec<Spanned<StatusResponse>>,
+    /// `[X-Rate-Limit:int]` after the responses, sent with every response.
+    pub response_headers: Vec<Spanned<Variable>>,
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+impl Endpoint {
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().map(|v| &v.node).find(|v| v.name == name)
+    }
+
+    /// The body returned with `status`, falling back to the default response.
+    pub fn response(&self, status: u16) -> Option<&Spanned<TypeExpr>> {
+        self.responses
+            .iter()
+            .find(|v| v.status == status)
+            .map(|v| &v.response_type)
+            .or(self.response_type.as_ref())
+    }
+}
+
+/// `404: NotFound` in `-> 200: User | 404: NotFound`
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct StatusResponse {
+    pub status: u16,
+    pub response_type: Spanned<TypeExpr>,
+    pub media_type: Option<Spanned<MediaType>>,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Method {
     GET,
     POST,
     PUT,
     DELETE,
+    PATCH,
+    HEAD,
+    OPTIONS,
+    TRACE,
+    CONNECT,
+    /// Any other method token, e.g. `PURGE` or WebDAV's `PROPFIND`.
+    Extension(String),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::GET => "GET",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+            Self::DELETE => "DELETE",
+            Self::PATCH => "PATCH",
+            Self::HEAD => "HEAD",
+            Self::OPTIONS => "OPTIONS",
+            Self::TRACE => "TRACE",
+            Self::CONNECT => "CONNECT",
+            Self::Extension(method) => method,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Path {
     Segment(String),
-    Variable(String, VariableType),
+    Variable(Variable),
+    /// `/{*path:string}`, matches the rest of the path including slashes.
+    /// Only the last segment of a path may be one.
+    CatchAll(Variable),
+    /// A `/` ending the path. The root path `GET /` has only this.
+    TrailingSlash,
+    /// A segment of literal text and variables, e.g. `/{id:long}.{format:string}`.
+    /// Two variables always have literal text between them.
+    Mixed(Vec<PathPart>),
 }
 
-#[derive(Debug)]
-pub struct QueryParam(String, VariableType);
+/// A part of a [`Path::Mixed`] segment.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum PathPart {
+    Literal(String),
+    Variable(Variable),
+}
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum VariableType {
+    /// UTF-8 text.
     String,
+    /// Signed 16-bit integer.
     Short,
+    /// Signed 32-bit integer.
     Int,
+    /// Signed 64-bit integer.
     Long,
+    /// Signed 8-bit integer.
+    Byte,
+    /// Unsigned 8-bit integer.
+    U8,
+    /// Unsigned 16-bit integer.
+    U16,
+    /// Unsigned 32-bit integer.
+    U32,
+    /// Unsigned 64-bit integer.
+    U64,
+    /// Signed 128-bit integer.
+    I128,
+    /// IEEE 754 single precision float.
     Float,
+    /// IEEE 754 double precision float.
     Double,
+    /// `true` or `false`.
     Bool,
+    /// Arbitrary precision decimal number, written as text so no precision
+    /// is lost, e.g. `12.50`.
+    Decimal,
+    /// RFC 4122 UUID in its hyphenated text form.
+    Uuid,
+    /// RFC 3339 `full-date`, e.g. `2023-08-01`.
+    Date,
+    /// RFC 3339 `date-time` with offset, e.g. `2023-08-01T12:00:00Z`.
+    DateTime,
+    /// ISO 8601 duration, e.g. `PT5M`.
+    Duration,
+    /// RFC 5322 `addr-spec`, e.g. `user@example.com`.
+    Email,
+    /// RFC 3986 absolute URI.
+    Url,
+    /// Raw bytes, base64 encoded (RFC 4648) wherever text is expected.
+    Binary,
+    /// A value of an enum declared with `enum Name { ... }`.
+    Enum(TypeName),
+}
+
+impl VariableType {
+    /// Type names accepted by the grammar.
+    pub const NAMES: &'static [&'static str] = &[
+        "string", "short", "int", "long", "byte", "u8", "u16", "u32", "u64", "i128", "float",
+        "double", "bool", "decimal", "uuid", "date", "datetime", "duration", "email", "url",
+        "binary",
+    ];
+
+    /// The name of the type in IDL source.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::String => "string",
+            Self::Short => "short",
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Byte => "byte",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::I128 => "i128",
+            Self::Float => "float",
+            Self::Double => "double",
+            Self::Bool => "bool",
+            Self::Decimal => "decimal",
+            Self::Uuid => "uuid",
+            Self::Date => "date",
+            Self::DateTime => "datetime",
+            Self::Duration => "duration",
+            Self::Email => "email",
+            Self::Url => "url",
+            Self::Binary => "binary",
+            Self::Enum(name) => name,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Self::Short
+                | Self::Int
+                | Self::Long
+                | Self::Byte
+                | Self::U8
+                | Self::U16
+                | Self::U32
+                | Self::U64
+                | Self::I128
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::Float | Self::Double)
+    }
+
+    /// Integers, floats and decimals.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float() || *self == Self::Decimal
+    }
+
+    /// Inclusive value range of an integer type.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        Some(match self {
+            Self::Byte => (i8::MIN.into(), i8::MAX.into()),
+            Self::Short => (i16::MIN.into(), i16::MAX.into()),
+            Self::Int => (i32::MIN.into(), i32::MAX.into()),
+            Self::Long => (i64::MIN.into(), i64::MAX.into()),
+            Self::I128 => (i128::MIN, i128::MAX),
+            Self::U8 => (0, u8::MAX.into()),
+            Self::U16 => (0, u16::MAX.into()),
+            Self::U32 => (0, u32::MAX.into()),
+            Self::U64 => (0, u64::MAX.into()),
+            _ => return None,
+        })
+    }
 }
 
+/// A request body and the media type it is sent as.
 #[derive(Debug)]
-pub struct RequestType(String);
+pub struct RequestType(Spanned<TypeExpr>, Option<Spanned<MediaType>>);
+/// A default response body and the media type it is returned as.
 #[derive(Debug)]
-pub struct ResponseType(String);
+pub struct ResponseType(Spanned<TypeExpr>, Option<Spanned<MediaType>>);
 
 impl TryFrom<Pair<'_, Rule>> for VariableType {
     type Error = ParseError;
@@ -126,32 +511,204 @@ impl TryFrom<Pair<'_, Rule>> for VariableType {
                 "short" => Ok(Self::Short),
                 "int" => Ok(Self::Int),
                 "long" => Ok(Self::Long),
+                "byte" => Ok(Self::Byte),
+                "u8" => Ok(Self::U8),
+                "u16" => Ok(Self::U16),
+                "u32" => Ok(Self::U32),
+                "u64" => Ok(Self::U64),
+                "i128" => Ok(Self::I128),
                 "float" => Ok(Self::Float),
                 "double" => Ok(Self::Double),
                 "bool" => Ok(Self::Bool),
-                _ => Err(ParseError::UnsupportType),
+                "decimal" => Ok(Self::Decimal),
+                "uuid" => Ok(Self::Uuid),
+                "date" => Ok(Self::Date),
+                "datetime" => Ok(Self::DateTime),
+                "duration" => Ok(Self::Duration),
+                "email" => Ok(Self::Email),
+                "url" => Ok(Self::Url),
+                "binary" => Ok(Self::Binary),
+                name => Err(ParseError::UnsupportType {
+                    name: name.to_owned(),
+                    span: value.as_span().into(),
+                }),
             }
+        } else if Rule::enum_ref == value.as_rule() {
+            Ok(Self::Enum(value.as_str().to_owned()))
         } else {
-            Err(ParseError::UnexpectRule)
+            Err(ParseError::unexpect_rule(Rule::variable_type, &value))
         }
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd)]
-pub struct Variable(String, VariableType);
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Variable {
+    pub name: String,
+    pub variable_type: Spanned<VariableType>,
+    /// `name?:type`, the parameter may be left out.
+    pub optional: bool,
+    /// `name:type=value`, the value used when the parameter is left out.
+    pub default: Option<Spanned<Literal>>,
+    /// `name:[type]`, the parameter holds a list of `variable_type` values
+    /// written to the URL in this style.
+    pub list: Option<ListStyle>,
+    /// `name:int(1..=100)`, for a list they apply to each item.
+    pub constraints: Box<Constraints>,
+}
+
+/// How the values of a list query parameter are written to the URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ListStyle {
+    /// `?tag=a&tag=b`, declared as `tag:[string]` or `tag:[string; repeat]`.
+    Repeated,
+    /// `?ids=1,2,3`, declared as `ids:[int; csv]`.
+    CommaSeparated,
+}
+
+impl ListStyle {
+    /// Splits the raw values of every occurrence of a key into list items.
+    pub fn decode<'a>(&self, raw: &[&'a str]) -> Vec<&'a str> {
+        match self {
+            Self::Repeated => raw.to_vec(),
+            Self::CommaSeparated => raw
+                .iter()
+                .flat_map(|v| v.split(','))
+                .filter(|v| !v.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Writes `items` as the `(key, value)` pairs of a query string. Items
+    /// are expected to be percent-encoded already.
+    pub fn encode(&self, name: &str, items: &[&str]) -> Vec<(String, String)> {
+        match self {
+            Self::Repeated => items
+                .iter()
+                .map(|v| (name.to_owned(), v.to_string()))
+                .collect(),
+            Self::CommaSeparated if items.is_empty() => vec![],
+            Self::CommaSeparated => vec![(name.to_owned(), items.join(","))],
+        }
+    }
+}
+
+impl Variable {
+    pub fn new(name: impl Into<String>, variable_type: VariableType) -> Self {
+        Self {
+            name: name.into(),
+            variable_type: variable_type.into(),
+            optional: false,
+            default: None,
+            list: None,
+            constraints: Box::default(),
+        }
+    }
+
+    pub fn list(mut self, style: ListStyle) -> Self {
+        self.list = Some(style);
+        self
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    pub fn with_default(mut self, default: Literal) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn with_constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = Box::new(constraints);
+        self
+    }
+
+    /// Whether a request has to provide the parameter.
+    pub fn is_required(&self) -> bool {
+        !self.optional && self.default.is_none()
+    }
+}
 
 impl TryFrom<Pair<'_, Rule>> for Variable {
     type Error = ParseError;
 
     fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
-        if Rule::variable == value.as_rule() {
-            let mut pairs = value.into_inner();
-            let name = pairs.next().unwrap();
-            let variable_type = pairs.next().unwrap().try_into()?;
+        if matches!(value.as_rule(), Rule::variable | Rule::parameter | Rule::header) {
+            let mut pairs = Children::of(value);
+            let name = pairs.next_pair()?;
+            let mut pair = pairs.next_pair()?;
+            let optional = pair.as_rule() == Rule::optional;
+            if optional {
+                pair = pairs.next_pair()?;
+            }
+            let (variable_type, constraints, list) = if pair.as_rule() == Rule::list_type {
+                let mut pairs = Children::of(pair);
+                let variable_type: Spanned<VariableType> = spanned(pairs.next_pair()?)?;
+                let constraints = pairs.next_if(Rule::constraints);
+                let style = match pairs.next() {
+                    Some(style) => Some(style.try_into()?),
+                    None => Some(ListStyle::Repeated),
+                };
+                (variable_type, constraints, style)
+            } else {
+                (spanned(pair)?, pairs.next_if(Rule::constraints), None)
+            };
+            let written = constraints.as_ref().map_or("", |v| v.as_str());
+            let constraints = match constraints {
+                Some(pair) => Constraints::of_type(pair, &variable_type)?,
+                None => Constraints::default(),
+            };
+            let default = match pairs.next() {
+                Some(default) if list.is_some() => {
+                    return Err(ParseError::InvalidDefault {
+                        value: default.as_str().to_owned(),
+                        expected: format!("[{}{written}]", variable_type.as_str()),
+                        span: default.as_span().into(),
+                    })
+                }
+                Some(default) => {
+                    let value = default.as_str().to_owned();
+                    let literal = Literal::of_type(default, &variable_type)?;
+                    if !constraints.admits(&literal) {
+                        return Err(ParseError::InvalidDefault {
+                            value,
+                            expected: format!("{}{written}", variable_type.as_str()),
+                            span: literal.span,
+                        });
+                    }
+                    Some(literal)
+                }
+                None => None,
+            };
 
-            Ok(Self(name.as_str().to_owned(), variable_type))
+            Ok(Self {
+                name: name.as_str().to_owned(),
+                variable_type,
+                optional,
+                default,
+                list,
+                constraints: Box::new(constraints),
+            })
         } else {
-            Err(ParseError::UnexpectRule)
+            Err(ParseError::unexpect_rule(Rule::variable, &value))
+        }
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for ListStyle {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::list_style == value.as_rule() {
+            let style = Children::of(value).next_pair()?;
+            match style.as_rule() {
+                Rule::repeat => Ok(Self::Repeated),
+                Rule::csv => Ok(Self::CommaSeparated),
+                _ => Err(ParseError::unexpect_rule(Rule::list_style, &style)),
+            }
+        } else {
+            Err(ParseError::unexpect_rule(Rule::list_style, &value))
         }
     }
 }
@@ -161,16 +718,44 @@ impl TryFrom<Pair<'_, Rule>> for Method {
 
     fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
         if Rule::method == value.as_rule() {
-            let Some(method) = value.into_inner().next() else {panic!("Impossible")};
-            Ok(match method.as_str().to_uppercase().as_str() {
-                "GET" => Self::GET,
-                "POST" => Self::POST,
-                "PUT" => Self::PUT,
-                "DELETE" => Self::DELETE,
-                _ => panic!("Impossible"),
-            })
+            let method = Children::of(value).next_pair()?;
+            match method.as_rule() {
+                Rule::get => Ok(Self::GET),
+                Rule::post => Ok(Self::POST),
+                Rule::put => Ok(Self::PUT),
+                Rule::delete => Ok(Self::DELETE),
+                Rule::patch => Ok(Self::PATCH),
+                Rule::head => Ok(Self::HEAD),
+                Rule::options => Ok(Self::OPTIONS),
+                Rule::trace => Ok(Self::TRACE),
+                Rule::connect => Ok(Self::CONNECT),
+                Rule::extension_method => Ok(Self::Extension(method.as_str().to_owned())),
+                _ => Err(ParseError::unexpect_rule(Rule::method, &method)),
+            }
         } else {
-            Err(ParseError::UnexpectRule)
+            Err(ParseError::unexpect_rule(Rule::method, &value))
+        }
+    }
+}
+
+impl Path {
+    /// The variables of the segment, in order.
+    pub fn variables(&self) -> impl Iterator<Item = &Variable> {
+        let (variable, parts) = match self {
+            Self::Segment(_) | Self::TrailingSlash => (None, &[][..]),
+            Self::Variable(variable) | Self::CatchAll(variable) => (Some(variable), &[][..]),
+            Self::Mixed(parts) => (None, parts.as_slice()),
+        };
+        variable.into_iter().chain(parts.iter().filter_map(|v| match v {
+            PathPart::Literal(_) => None,
+            PathPart::Variable(variable) => Some(variable),
+        }))
+    }
+
+    pub fn catch_all(&self) -> Option<&Variable> {
+        match self {
+            Self::CatchAll(variable) => Some(variable),
+            _ => None,
         }
     }
 }
@@ -181,42 +766,187 @@ impl TryFrom<Pair<'_, Rule>> for Path {
     fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
         if Rule::segment == value.as_rule() {
             Ok(Path::Segment(
-                value.into_inner().next().unwrap().as_str().to_string(),
+                Children::of(value).next_pair()?.as_str().to_string(),
             ))
-        } else if Rule::variable == value.as_rule() {
-            let Variable(name, var_type) = value.try_into()?;
-            Ok(Path::Variable(name, var_type))
+        } else if Rule::path_variable == value.as_rule() {
+            Ok(Path::Variable(Children::of(value).next_pair()?.try_into()?))
+        } else if Rule::mixed_segment == value.as_rule() {
+            let parts = value
+                .into_inner()
+                .map(|v| match v.as_rule() {
+                    Rule::segment_text => Ok(PathPart::Literal(v.as_str().to_owned())),
+                    _ => Ok(PathPart::Variable(v.try_into()?)),
+                })
+                .collect::<Result<_, ParseError>>()?;
+            Ok(Path::Mixed(parts))
+        } else if Rule::trailing_slash == value.as_rule() {
+            Ok(Path::TrailingSlash)
+        } else if Rule::catch_all == value.as_rule() {
+            let variable: Variable = Children::of(value).next_pair()?.try_into()?;
+            if *variable.variable_type != VariableType::String {
+                return Err(ParseError::InvalidCatchAll {
+                    name: variable.name,
+                    reason: "it must have type string".to_owned(),
+                    span: variable.variable_type.span,
+                });
+            }
+            Ok(Path::CatchAll(variable))
+        } else {
+            Err(ParseError::unexpect_rule(Rule::segment, &value))
+        }
+    }
+}
+
+/// Child pairs of a rule. The grammar guarantees the children a conversion
+/// expects, but a mismatch between grammar and conversion must surface as an
+/// error rather than a panic.
+struct Children<'i> {
+    rule: Rule,
+    span: Span,
+    pairs: Pairs<'i, Rule>,
+}
+
+impl<'i> Children<'i> {
+    fn of(pair: Pair<'i, Rule>) -> Self {
+        Self {
+            rule: pair.as_rule(),
+            span: pair.as_span().into(),
+            pairs: pair.into_inner(),
+        }
+    }
+
+    fn next_pair(&mut self) -> Result<Pair<'i, Rule>, ParseError> {
+        self.pairs.next().ok_or(ParseError::UnexpectEnd {
+            rule: self.rule,
+            span: self.span,
+        })
+    }
+
+    /// The next pair if it is a `rule`.
+    fn next_if(&mut self, rule: Rule) -> Option<Pair<'i, Rule>> {
+        if self.pairs.peek()?.as_rule() == rule {
+            self.pairs.next()
         } else {
-            Err(ParseError::UnexpectRule)
+            None
+        }
+    }
+
+    /// Like `next_pair`, but first collects the `///` lines in front of the
+    /// pair into `docs`.
+    fn next_documented(
+        &mut self,
+        docs: &mut Vec<&'i str>,
+    ) -> Result<Pair<'i, Rule>, ParseError> {
+        loop {
+            let pair = self.next_pair()?;
+            if pair.as_rule() != Rule::doc_comment {
+                return Ok(pair);
+            }
+            docs.push(pair.as_str());
+        }
+    }
+}
+
+/// Joins `///` lines into the text they document, without the slashes and
+/// the space after them.
+fn join_docs(lines: &[&str]) -> Option<String> {
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|v| v.trim_start_matches("///"))
+        .map(|v| v.strip_prefix(' ').unwrap_or(v).trim_end())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+impl<'i> Iterator for Children<'i> {
+    type Item = Pair<'i, Rule>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pairs.next()
+    }
+}
+
+/// Converts a `path` pair, checking a catch-all comes last.
+fn path_of(pair: Pair<'_, Rule>) -> Result<Vec<Spanned<Path>>, ParseError> {
+    let path = pair
+        .into_inner()
+        .map(spanned)
+        .collect::<Result<Vec<Spanned<Path>>, _>>()?;
+    let inner = path.split_last().map_or(&[][..], |v| v.1);
+    for segment in inner {
+        if let Some(variable) = segment.catch_all() {
+            return Err(ParseError::InvalidCatchAll {
+                name: variable.name.clone(),
+                reason: "it must be the last segment".to_owned(),
+                span: segment.span,
+            });
         }
     }
+    Ok(path)
+}
+
+/// The path of an `import`, without quotes.
+fn import_path(pair: Pair<'_, Rule>) -> Result<Spanned<String>, ParseError> {
+    let path = Children::of(pair).next_pair()?;
+    Ok(Spanned::new(literal::unescape(path.as_str()), path.as_span().into()))
+}
+
+/// Converts `pair` into `T`, keeping the span it was parsed from.
+fn spanned<'i, T>(pair: Pair<'i, Rule>) -> Result<Spanned<T>, ParseError>
+where
+    T: TryFrom<Pair<'i, Rule>, Error = ParseError>,
+{
+    let span = pair.as_span().into();
+    Ok(Spanned::new(pair.try_into()?, span))
 }
 
-macro_rules! impl_string_type {
+macro_rules! impl_type_wrapper {
     ($type:ident, $rule:expr, $r:ty) => {
         impl TryFrom<$r> for $type {
             type Error = ParseError;
 
             fn try_from(value: $r) -> Result<Self, Self::Error> {
                 if $rule == value.as_rule() {
-                    Ok($type(value.as_str().to_string()))
+                    let mut pairs = Children::of(value.clone());
+                    let body = spanned(pairs.next_pair()?)?;
+                    Ok($type(body, pairs.next().map(spanned).transpose()?))
                 } else {
-                    Err(ParseError::UnexpectRule)
+                    Err(ParseError::unexpect_rule($rule, &value))
                 }
             }
         }
     };
 }
 
-impl_string_type!(ResponseType, Rule::response_type, Pair<'_, Rule>);
-impl_string_type!(RequestType, Rule::request_type, Pair<'_, Rule>);
+impl TryFrom<Pair<'_, Rule>> for StatusResponse {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::status_response == value.as_rule() {
+            let mut pairs = Children::of(value);
+            let status = pairs.next_pair()?;
+            Ok(Self {
+                // the grammar only admits 100..=599
+                status: status.as_str().parse().unwrap_or_default(),
+                response_type: spanned(pairs.next_pair()?)?,
+                media_type: pairs.next().map(spanned).transpose()?,
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::status_response, &value))
+        }
+    }
+}
+
+impl_type_wrapper!(ResponseType, Rule::response_type, Pair<'_, Rule>);
+impl_type_wrapper!(RequestType, Rule::request_type, Pair<'_, Rule>);
 
-impl_string_type!(ResponseType, Rule::response_type, &Pair<'_, Rule>);
-impl_string_type!(RequestType, Rule::request_type, &Pair<'_, Rule>);
+impl_type_wrapper!(ResponseType, Rule::response_type, &Pair<'_, Rule>);
+impl_type_wrapper!(RequestType, Rule::request_type, &Pair<'_, Rule>);
 
 #[cfg(test)]
 mod tests {
     use pest::Parser;
+    use proptest::prelude::*;
 
     use super::*;
     use crate::EndpointParser;
@@ -236,6 +966,32 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_method_casing_and_extension() -> anyhow::Result<()> {
+        for (input, expected) in [
+            ("patch", Method::PATCH),
+            ("Head", Method::HEAD),
+            ("oPtIoNs", Method::OPTIONS),
+            ("TRACE", Method::TRACE),
+            ("connect", Method::CONNECT),
+            ("PURGE", Method::Extension("PURGE".to_owned())),
+            ("PROPFIND", Method::Extension("PROPFIND".to_owned())),
+            ("GETALL", Method::Extension("GETALL".to_owned())),
+        ] {
+            let mut pairs = EndpointParser::parse(Rule::method, input)?;
+            let method: Method = pairs.next().unwrap().try_into()?;
+            assert_eq!(expected, method);
+        }
+
+        let document = EndpointParser::parse_document("Get /a\nPURGE /cache/{key:string}\n")?;
+        assert_eq!(Method::GET, document.endpoints[0].method.node);
+        assert_eq!(
+            Method::Extension("PURGE".to_owned()),
+            document.endpoints[1].method.node
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_path() -> anyhow::Result<()> {
         let mut pairs = EndpointParser::parse(Rule::path, "/seg/{var:string}")?;
@@ -272,17 +1028,24 @@ mod tests {
         )?;
         assert_eq!(
             Endpoint {
-                method: Method::GET,
+                docs: None,
+                annotations: vec![],
+                method: Method::GET.into(),
                 path: vec![
-                    Path::Segment("register".to_owned()),
-                    Path::Variable("id".to_owned(), VariableType::String)
+                    Path::Segment("register".to_owned()).into(),
+                    Path::Variable(Variable::new("id", VariableType::String)).into()
                 ],
                 query_params: vec![
-                    Variable("type".to_owned(), VariableType::String),
-                    Variable("order".to_owned(), VariableType::String),
+                    Variable::new("type", VariableType::String).into(),
+                    Variable::new("order", VariableType::String).into(),
                 ],
-                request_type: Some("RQ".to_owned()),
-                response_type: Some("RS".to_owned())
+                headers: vec![],
+                request_type: Some(TypeExpr::named("RQ").into()),
+                request_media_type: None,
+                response_type: Some(TypeExpr::named("RS").into()),
+                response_media_type: None,
+                responses: vec![],
+                response_headers: vec![]
             },
             endpoint
         );
@@ -295,17 +1058,24 @@ mod tests {
             EndpointParser::parse_endpoint("GET /register/{id:string}?type:string&order:string ")?;
         assert_eq!(
             Endpoint {
-                method: Method::GET,
+                docs: None,
+                annotations: vec![],
+                method: Method::GET.into(),
                 path: vec![
-                    Path::Segment("register".to_owned()),
-                    Path::Variable("id".to_owned(), VariableType::String)
+                    Path::Segment("register".to_owned()).into(),
+                    Path::Variable(Variable::new("id", VariableType::String)).into()
                 ],
                 query_params: vec![
-                    Variable("type".to_owned(), VariableType::String),
-                    Variable("order".to_owned(), VariableType::String),
+                    Variable::new("type", VariableType::String).into(),
+                    Variable::new("order", VariableType::String).into(),
                 ],
+                headers: vec![],
                 request_type: None,
-                response_type: None
+                request_media_type: None,
+                response_type: None,
+                response_media_type: None,
+                responses: vec![],
+                response_headers: vec![]
             },
             endpoint
         );
@@ -317,18 +1087,1121 @@ mod tests {
             EndpointParser::parse_endpoint("GET /register/{id:string} RQ -> RS")?;
         assert_eq!(
             Endpoint {
-                method: Method::GET,
+                docs: None,
+                annotations: vec![],
+                method: Method::GET.into(),
                 path: vec![
-                    Path::Segment("register".to_owned()),
-                    Path::Variable("id".to_owned(), VariableType::String)
+                    Path::Segment("register".to_owned()).into(),
+                    Path::Variable(Variable::new("id", VariableType::String)).into()
                 ],
                 query_params: vec![
                 ],
-                request_type: Some("RQ".to_owned()),
-                response_type: Some("RS".to_owned())
+                headers: vec![],
+                request_type: Some(TypeExpr::named("RQ").into()),
+                request_media_type: None,
+                response_type: Some(TypeExpr::named("RS").into()),
+                response_media_type: None,
+                responses: vec![],
+                response_headers: vec![]
             },
             endpoint
         );
         Ok(())
     }
+
+    #[test]
+    fn test_document() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "// users
+GET /users/{id:string} RQ -> RS
+
+/* block
+   comment */
+POST /users?dry:bool UserRQ -> UserRS
+DELETE /users/{id:string}
+
+type RQ {}
+type RS {}
+type UserRQ { name: string }
+type UserRS { id: long, name: string }
+",
+        )?;
+        assert_eq!(
+            vec![Method::GET, Method::POST, Method::DELETE],
+            document
+                .endpoints
+                .into_iter()
+                .map(|v| v.method.node)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_endpoint_without_sig() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document("GET /a\nGET /b\n")?;
+        assert_eq!(2, document.endpoints.len());
+        assert_eq!(None, document.endpoints[0].request_type);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_document() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document("\n// nothing here\n\n")?;
+        assert!(document.endpoints.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_rejects_garbage() {
+        assert!(EndpointParser::parse_document("GET /a RQ -> RS junk").is_err());
+    }
+
+    #[test]
+    fn test_endpoint_spans() -> anyhow::Result<()> {
+        let document =
+            EndpointParser::parse_document("GET /a\nPOST /users/{id:string}?dry:bool RQ -> RS\ntype RQ {}\ntype RS {}")?;
+        let endpoint = &document.endpoints[1];
+        let span = |start, end, line, col| Span {
+            start,
+            end,
+            line,
+            col,
+        };
+        assert_eq!(span(7, 11, 2, 1), endpoint.method.span);
+        assert_eq!(span(12, 18, 2, 6), endpoint.path[0].span);
+        assert_eq!(span(18, 30, 2, 12), endpoint.path[1].span);
+        assert_eq!(span(31, 39, 2, 25), endpoint.query_params[0].span);
+        assert_eq!(
+            Some(span(40, 42, 2, 34)),
+            endpoint.request_type.as_ref().map(|v| v.span)
+        );
+        assert_eq!(
+            Some(span(46, 48, 2, 40)),
+            endpoint.response_type.as_ref().map(|v| v.span)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_conversion_rejects_wrong_rule() -> anyhow::Result<()> {
+        let mut pairs = EndpointParser::parse(Rule::path, "/seg")?;
+        let pair = pairs.next().unwrap();
+        assert!(matches!(
+            Method::try_from(pair.clone()),
+            Err(ParseError::UnexpectRule { .. })
+        ));
+        assert!(matches!(
+            Endpoint::try_from(pair),
+            Err(ParseError::UnexpectRule { .. })
+        ));
+        Ok(())
+    }
+
+    /// Input that looks like IDL, most of it with a mistake or two.
+    const IDL_LIKE: &str = "((import \"[a-z.]{0,3}\"?\n)?(service [A-Z]{0,2} ?/[a-z]{0,2} ?\\{?\n?)?(///?[a-z ]{0,3}\n)?(@[a-z]{0,3}(\\([a-z]{0,2}\\))? ?\n?)?(GET|get|POST|DELETE|PUT|Patch|HEAD|PURGE)[ \t]?(/[a-z0-9._~%-]{0,3}|/[a-z.]{0,2}\\{\\*?[a-z]{0,2}:?[a-z]{0,6}\\}?[a-z.]{0,2}){0,3}/?(\n? ?\\? ?[a-z]{0,2} ?:?[a-z]{0,6}(\\([0-9.=,len]{0,5}\\)?)?(&[a-z]:[a-z]{0,6})?)?( ?\\[[A-Z][a-z-]{0,3}\\??:[a-z]{0,6}\\]?)? ?[A-Z]{0,2}(\n?[ \t]?-> ?([1-6]0[0-9]: ?)?[A-Z]{0,2}(<[A-Z]{0,2}(, ?[A-Z])?>?)?( as [a-z/]{0,5})?( ?\\| ?[A-Z])?)?(\n|//[ a-z]*\n|/\\*|\n?\\})?){0,4}";
+
+    proptest! {
+        #[test]
+        fn prop_parse_never_panics(input in "\\PC*") {
+            let _ = EndpointParser::parse_endpoint(&input);
+            let _ = EndpointParser::parse_document(&input);
+            let _ = EndpointParser::parse_document_recovering(&input);
+        }
+
+        #[test]
+        fn prop_parse_idl_like_never_panics(
+            input in IDL_LIKE
+        ) {
+            let _ = EndpointParser::parse_endpoint(&input);
+            let _ = EndpointParser::parse_document(&input);
+            let _ = EndpointParser::parse_document_recovering(&input);
+        }
+
+        #[test]
+        fn prop_print_roundtrip(input in IDL_LIKE) {
+            if let Ok(document) = EndpointParser::parse_rule::<Document>(Rule::document, &input) {
+                let printed = document.to_string();
+                let reparsed = EndpointParser::parse_rule::<Document>(Rule::document, &printed);
+                prop_assert_eq!(Some(document), reparsed.ok(), "{}", printed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_recovering() {
+        let (document, errors) = EndpointParser::parse_document_recovering(
+            "GET /a RQ -> RS
+GET /b/{id:strng}
+// still fine
+POST /c?x:int RQ -> RS junk
+  more junk
+DELETE /d
+PUT /e/{id:bad}
+type RQ {}
+type RS {}",
+        );
+        assert_eq!(
+            vec![Method::GET, Method::GET, Method::DELETE, Method::PUT],
+            document
+                .endpoints
+                .iter()
+                .map(|v| v.method.node.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(2, 12), (4, 24), (7, 12)],
+            errors
+                .iter()
+                .map(|v| (v.span().line, v.span().col))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(&errors[0], ParseError::UnsupportType { name, .. } if name == "strng"));
+        assert!(matches!(&errors[1], ParseError::Syntax { found, .. } if found == "junk"));
+
+        // indented items are found again whether spaces or tabs indent them
+        for indent in ["    ", "\t"] {
+            let input = format!("GET /a ->\n{indent}GET /b\nGET /c");
+            let (document, errors) = EndpointParser::parse_document_recovering(&input);
+            assert_eq!(2, document.endpoints.len(), "{input:?}");
+            assert_eq!(1, errors.len());
+        }
+
+        // a broken endpoint in a service costs that endpoint only
+        let (document, errors) = EndpointParser::parse_document_recovering(
+            "service S /s {\n  GET /a/{x:int=1}\n  service T /t {\n    GET /x/{\n    GET /y\n  }\n  GET /b\n}\nGET /c",
+        );
+        assert_eq!(
+            vec![(2, 16), (4, 13)],
+            errors.iter().map(|v| (v.span().line, v.span().col)).collect::<Vec<_>>()
+        );
+        let paths: Vec<String> = document
+            .all_endpoints()
+            .map(|v| v.path.iter().map(|v| v.to_string()).collect())
+            .collect();
+        assert_eq!(vec!["/c", "/s/b", "/s/t/y"], paths);
+    }
+
+    #[test]
+    fn test_document_recovering_without_errors() -> anyhow::Result<()> {
+        let input = "GET /a RQ -> RS\n\n/* c */\nDELETE /d\ntype RQ {}\ntype RS {}\n";
+        let (document, errors) = EndpointParser::parse_document_recovering(input);
+        assert!(errors.is_empty());
+        assert_eq!(EndpointParser::parse_document(input)?, document);
+
+        // a doc comment after an item documents the next one
+        let input = "GET /a /// The second.\nGET /b";
+        let (document, errors) = EndpointParser::parse_document_recovering(input);
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(EndpointParser::parse_document(input)?, document);
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable_types() -> anyhow::Result<()> {
+        for name in VariableType::NAMES {
+            let input = format!("x:{name}");
+            let mut pairs = EndpointParser::parse(Rule::variable, &input)?;
+            let variable: Variable = pairs.next().unwrap().try_into()?;
+            assert_eq!(*name, variable.variable_type.as_str());
+        }
+
+        let document = EndpointParser::parse_document("GET /a/{x:byte}/{at:datetime}?d:date")?;
+        let path: Vec<Spanned<Path>> = vec![
+            Path::Segment("a".to_owned()).into(),
+            Path::Variable(Variable::new("x", VariableType::Byte)).into(),
+            Path::Variable(Variable::new("at", VariableType::DateTime)).into(),
+        ];
+        assert_eq!(path, document.endpoints[0].path);
+        assert!(EndpointParser::parse_document("GET /a/{x:u128}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_and_default_params() -> anyhow::Result<()> {
+        let endpoint = EndpointParser::parse_endpoint(
+            r#"GET /users?page:int=1&size:u8=20&filter?:string&sort:string="name asc"&since?:date=2023-08-01"#,
+        )?;
+        let params: Vec<Spanned<Variable>> = vec![
+            Variable::new("page", VariableType::Int)
+                .with_default(Literal::Integer(1))
+                .into(),
+            Variable::new("size", VariableType::U8)
+                .with_default(Literal::Integer(20))
+                .into(),
+            Variable::new("filter", VariableType::String)
+                .optional()
+                .into(),
+            Variable::new("sort", VariableType::String)
+                .with_default(Literal::String("name asc".to_owned()))
+                .into(),
+            Variable::new("since", VariableType::Date)
+                .optional()
+                .with_default(Literal::String("2023-08-01".to_owned()))
+                .into(),
+        ];
+        assert_eq!(params, endpoint.query_params);
+        assert!(!endpoint.query_params[0].is_required());
+        assert_eq!(
+            Some((20, 21)),
+            endpoint.query_params[0]
+                .default
+                .as_ref()
+                .map(|v| (v.span.start, v.span.end))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        for input in [
+            "GET /a?page:int=abc",
+            "GET /a?b:bool=yes",
+            "GET /a?x:byte=300",
+            "GET /a?x:int=\"1\"",
+            "GET /a?id:uuid=42",
+        ] {
+            let err = EndpointParser::parse_document(input).unwrap_err();
+            assert!(matches!(err, ParseError::InvalidDefault { .. }), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_path_variable_cannot_be_optional() {
+        assert!(EndpointParser::parse_document("GET /a/{id?:int}").is_err());
+        assert!(EndpointParser::parse_document("GET /a/{id:int=1}").is_err());
+    }
+
+    #[test]
+    fn test_list_params() -> anyhow::Result<()> {
+        let endpoint = EndpointParser::parse_endpoint(
+            "GET /search?ids:[int; csv]&tag:[string]&order?:[string;repeat]",
+        )?;
+        let params: Vec<Spanned<Variable>> = vec![
+            Variable::new("ids", VariableType::Int)
+                .list(ListStyle::CommaSeparated)
+                .into(),
+            Variable::new("tag", VariableType::String)
+                .list(ListStyle::Repeated)
+                .into(),
+            Variable::new("order", VariableType::String)
+                .optional()
+                .list(ListStyle::Repeated)
+                .into(),
+        ];
+        assert_eq!(params, endpoint.query_params);
+
+        assert!(EndpointParser::parse_document("GET /a?ids:[int]=1").is_err());
+        assert!(EndpointParser::parse_document("GET /a?ids:[int; tsv]").is_err());
+        assert!(EndpointParser::parse_document("GET /a/{ids:[int]}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_style_encoding() {
+        let csv = ListStyle::CommaSeparated;
+        assert_eq!(vec!["1", "2", "3"], csv.decode(&["1,2", "3"]));
+        assert_eq!(
+            vec![("ids".to_owned(), "1,2".to_owned())],
+            csv.encode("ids", &["1", "2"])
+        );
+
+        let repeated = ListStyle::Repeated;
+        assert_eq!(vec!["a", "b"], repeated.decode(&["a", "b"]));
+        assert_eq!(
+            vec![
+                ("tag".to_owned(), "a".to_owned()),
+                ("tag".to_owned(), "b".to_owned())
+            ],
+            repeated.encode("tag", &["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_type_defs() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "type User { id: long, name: string, email?: string }
+GET /users/{id:long}
+type Team {
+    // members are users
+    owner: User
+    tags: string,
+}
+POST /teams Team -> Team",
+        )?;
+        assert_eq!(2, document.endpoints.len());
+        assert_eq!(2, document.types.len());
+
+        let user = document.type_def("User").unwrap();
+        let fields: Vec<Spanned<Field>> = vec![
+            Field::new("id", TypeExpr::Scalar(VariableType::Long)).into(),
+            Field::new("name", TypeExpr::Scalar(VariableType::String)).into(),
+            Field::new("email", TypeExpr::Scalar(VariableType::String))
+                .optional()
+                .into(),
+        ];
+        assert_eq!(fields, user.fields);
+        assert_eq!(
+            TypeExpr::named("User"),
+            document.type_def("Team").unwrap().field("owner").unwrap().field_type.node
+        );
+
+        let endpoint = &document.endpoints[1];
+        assert_eq!(Some("Team"), document.request_def(endpoint).map(|v| v.name.as_str()));
+        assert_eq!(Some("Team"), document.response_def(endpoint).map(|v| v.name.as_str()));
+        assert_eq!(None, document.request_def(&document.endpoints[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undeclared_types() {
+        let err = EndpointParser::parse_document("GET /a RQ -> RS\ntype RQ {}").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, span } if name == "RS" && span.line == 1));
+
+        let err = EndpointParser::parse_document("type A { b: B }").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, .. } if name == "B"));
+
+        let err = EndpointParser::parse_document("type A {}\ntype A {}").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, span } if name == "A" && span.line == 2));
+
+        let err = EndpointParser::parse_document("type A { x: int, x: long }").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, .. } if name == "x"));
+
+        // the first error in the source, declarations are checked before endpoints
+        let err = EndpointParser::parse_document("GET /a -> Y\ntype A { b: X }").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, .. } if name == "Y"), "{err:?}");
+
+        for input in ["type int { a: string }", "enum uuid { a }", "type List {}", "enum Option { a }"] {
+            let err = EndpointParser::parse_document(input).unwrap_err();
+            assert!(matches!(err, ParseError::ReservedName { span, .. } if span.col == 6), "{err:?}");
+        }
+
+        let (document, errors) =
+            EndpointParser::parse_document_recovering("GET /a X -> Y\ntype A { b:: int }\ntype B {}");
+        assert_eq!(1, document.endpoints.len());
+        assert_eq!(3, errors.len());
+    }
+
+    #[test]
+    fn test_enums() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "enum Order { asc, desc }
+enum Status {
+    in-progress
+    done
+}
+type Task { status: Status, title: string }
+GET /tasks/{status:Status}?order:Order=desc&also?:[Status; csv] -> Task",
+        )?;
+        let order = document.enum_def("Order").unwrap();
+        assert_eq!(vec!["asc", "desc"], order.values().collect::<Vec<_>>());
+
+        let endpoint = &document.endpoints[0];
+        let Path::Variable(status) = &endpoint.path[1].node else {
+            panic!("expected a path variable")
+        };
+        assert_eq!(
+            VariableType::Enum("Status".to_owned()),
+            status.variable_type.node
+        );
+        assert_eq!(
+            Some(vec!["in-progress", "done"]),
+            document.enum_of(status).map(|v| v.values().collect::<Vec<_>>())
+        );
+        let params: Vec<Spanned<Variable>> = vec![
+            Variable::new("order", VariableType::Enum("Order".to_owned()))
+                .with_default(Literal::String("desc".to_owned()))
+                .into(),
+            Variable::new("also", VariableType::Enum("Status".to_owned()))
+                .optional()
+                .list(ListStyle::CommaSeparated)
+                .into(),
+        ];
+        assert_eq!(params, endpoint.query_params);
+        Ok(())
+    }
+
+    #[test]
+    fn test_enum_errors() {
+        let err = EndpointParser::parse_document("GET /a?o:Ordr").unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportType { ref name, span } if name == "Ordr" && span.col == 10));
+
+        let err = EndpointParser::parse_document("type T {}\nGET /a/{t:T}").unwrap_err();
+        assert!(matches!(err, ParseError::NotAnEnum { ref name, .. } if name == "T"));
+
+        let err =
+            EndpointParser::parse_document("enum O { asc }\nGET /a?o:O=up").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDefault { ref value, .. } if value == "up"));
+
+        let err = EndpointParser::parse_document("enum O { a, a }").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, .. } if name == "a"));
+
+        let err = EndpointParser::parse_document("enum O { a }\ntype O {}").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, .. } if name == "O"));
+    }
+
+    #[test]
+    fn test_generic_types() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "type Page<T> { items: List<T>, total: long, next?: Option<string> }
+type User { tags: Map<string, List<string>> }
+GET /users -> List<User>
+GET /users/page -> Page<User>
+POST /users/bulk Map<uuid, User> -> Option<Page< User >>",
+        )?;
+        let params: Vec<Spanned<String>> = vec!["T".to_owned().into()];
+        assert_eq!(params, document.type_def("Page").unwrap().params);
+        assert_eq!(
+            TypeExpr::list(TypeExpr::named("T")),
+            document.type_def("Page").unwrap().field("items").unwrap().field_type.node
+        );
+        assert_eq!(
+            TypeExpr::map(
+                TypeExpr::Scalar(VariableType::String),
+                TypeExpr::list(TypeExpr::Scalar(VariableType::String))
+            ),
+            document.type_def("User").unwrap().field("tags").unwrap().field_type.node
+        );
+
+        let response_types: Vec<Option<Spanned<TypeExpr>>> = vec![
+            Some(TypeExpr::list(TypeExpr::named("User")).into()),
+            Some(TypeExpr::generic("Page", vec![TypeExpr::named("User")]).into()),
+            Some(TypeExpr::option(TypeExpr::generic("Page", vec![TypeExpr::named("User")])).into()),
+        ];
+        assert_eq!(response_types, document.endpoints.iter().map(|v| v.response_type.clone()).collect::<Vec<_>>());
+        assert_eq!(
+            Some(TypeExpr::map(TypeExpr::Scalar(VariableType::Uuid), TypeExpr::named("User"))),
+            document.endpoints[2].request_type.clone().map(|v| v.node)
+        );
+        assert_eq!(Some("Page"), document.response_def(&document.endpoints[1]).map(|v| v.name.as_str()));
+        assert_eq!(None, document.response_def(&document.endpoints[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_type_errors() {
+        let err = EndpointParser::parse_document("GET /a -> Map<string>").unwrap_err();
+        assert!(matches!(err, ParseError::TypeArity { ref name, expected: 2, found: 1, .. } if name == "Map"));
+
+        let err = EndpointParser::parse_document("type P<T> {}\nGET /a -> P").unwrap_err();
+        assert!(matches!(err, ParseError::TypeArity { ref name, expected: 1, found: 0, span } if name == "P" && span.line == 2));
+
+        let err = EndpointParser::parse_document("type P<T> { t: T<int> }").unwrap_err();
+        assert!(matches!(err, ParseError::TypeArity { ref name, expected: 0, .. } if name == "T"));
+
+        let err = EndpointParser::parse_document("type U {}\nGET /a -> List<Usr>").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, span } if name == "Usr" && span.col == 16));
+
+        let err = EndpointParser::parse_document("type P<T, T> {}").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, .. } if name == "T"));
+
+        let err = EndpointParser::parse_document("type P { t: T }").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, .. } if name == "T"));
+    }
+
+    #[test]
+    fn test_status_responses() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "POST /users User -> 201: User | 409: Conflict|Error
+DELETE /users/{id:long} -> 204: Empty
+type User {}
+type Conflict {}
+type Error {}
+type Empty {}",
+        )?;
+        let endpoint = &document.endpoints[0];
+        let responses: Vec<Spanned<StatusResponse>> = vec![
+            StatusResponse { status: 201, response_type: TypeExpr::named("User").into(), media_type: None }.into(),
+            StatusResponse { status: 409, response_type: TypeExpr::named("Conflict").into(), media_type: None }.into(),
+        ];
+        assert_eq!(responses, endpoint.responses);
+        assert_eq!(Some(TypeExpr::named("Error")), endpoint.response_type.clone().map(|v| v.node));
+        assert_eq!(Some(&TypeExpr::named("Conflict")), endpoint.response(409).map(|v| &v.node));
+        assert_eq!(Some(&TypeExpr::named("Error")), endpoint.response(500).map(|v| &v.node));
+        assert_eq!(None, document.endpoints[1].response(200));
+
+        let err = EndpointParser::parse_document("GET /a -> 200: A | 200: B").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, span } if name == "200" && span.col == 20));
+
+        let err = EndpointParser::parse_document("GET /a -> A | B").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { .. }));
+
+        let err = EndpointParser::parse_document("GET /a -> 200: A\ntype B {}").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, .. } if name == "A"));
+
+        assert!(EndpointParser::parse_document("GET /a -> 600: A\ntype A {}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_headers() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "POST /orders?dry:bool [X-Tenant-Id:string, Idempotency-Key?:uuid] Order -> 201: Order [X-Rate-Limit:int]
+GET /orders [Accept-Language?:[string;csv]] -> [ETag:string]
+DELETE /orders [X-Mode:Mode=soft]
+enum Mode { soft, hard }
+type Order {}",
+        )?;
+        let headers: Vec<Spanned<Variable>> = vec![
+            Variable::new("X-Tenant-Id", VariableType::String).into(),
+            Variable::new("Idempotency-Key", VariableType::Uuid).optional().into(),
+        ];
+        assert_eq!(headers, document.endpoints[0].headers);
+        let response_headers: Vec<Spanned<Variable>> =
+            vec![Variable::new("X-Rate-Limit", VariableType::Int).into()];
+        assert_eq!(response_headers, document.endpoints[0].response_headers);
+        assert_eq!(Some(TypeExpr::named("Order")), document.endpoints[0].request_type.clone().map(|v| v.node));
+
+        let get = &document.endpoints[1];
+        let headers: Vec<Spanned<Variable>> = vec![Variable::new("Accept-Language", VariableType::String)
+            .optional()
+            .list(ListStyle::CommaSeparated)
+            .into()];
+        assert_eq!(headers, get.headers);
+        assert_eq!(None, get.response_type);
+        assert_eq!("ETag", get.response_headers[0].name);
+        assert_eq!(Some("Mode"), document.enum_of(&document.endpoints[2].headers[0]).map(|v| v.name.as_str()));
+
+        let err = EndpointParser::parse_document("GET /a [X-Id:strng]").unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportType { ref name, .. } if name == "strng"));
+
+        let err = EndpointParser::parse_document("GET /a [X-Id:int=abc]").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDefault { ref value, .. } if value == "abc"));
+
+        let err = EndpointParser::parse_document("enum M { a }\nGET /a -> [X-M:M=b]").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDefault { ref value, .. } if value == "b"));
+
+        // header names are compared case-insensitively, a request and a
+        // response may both have the same header
+        let err = EndpointParser::parse_document("GET /a [X-Id:int, x-id:int]").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, span } if name == "x-id" && span.col == 19));
+        EndpointParser::parse_document("GET /a [X-Id:int] -> [X-Id:int]")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_media_types() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "POST /login Login as form -> Session
+POST /files Upload as multipart -> 201: File | 415: Error as text/plain
+GET /reports -> List<Report> as csv
+GET /invoices/{id:uuid} -> Invoice as application/pdf
+type Login {}
+type Session {}
+type Upload {}
+type File {}
+type Error {}
+type Report {}
+type Invoice {}",
+        )?;
+        let media_type = |v: &Option<Spanned<MediaType>>| v.clone().map(|v| v.node);
+        let endpoints = &document.endpoints;
+        assert_eq!(Some(MediaType::Form), media_type(&endpoints[0].request_media_type));
+        assert_eq!(None, media_type(&endpoints[0].response_media_type));
+        assert_eq!(Some(TypeExpr::named("Login")), endpoints[0].request_type.clone().map(|v| v.node));
+
+        assert_eq!(Some(MediaType::Multipart), media_type(&endpoints[1].request_media_type));
+        assert_eq!(None, media_type(&endpoints[1].responses[0].media_type));
+        assert_eq!(Some(MediaType::Text), media_type(&endpoints[1].responses[1].media_type));
+
+        assert_eq!(Some(MediaType::Csv), media_type(&endpoints[2].response_media_type));
+        assert_eq!(
+            Some(MediaType::Other("application/pdf".to_owned())),
+            media_type(&endpoints[3].response_media_type)
+        );
+
+        let err = EndpointParser::parse_document("GET /a -> A as yaml").unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportMediaType { ref name, span } if name == "yaml" && span.col == 16));
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotations() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "@deprecated(\"use /v2/users\") @internal
+@auth(bearer)
+@tag(billing, users)
+@timeout(5s)
+GET /users -> List<User>
+@owner
+POST /users
+type User {}",
+        )?;
+        let annotations: Vec<Spanned<Annotation>> = vec![
+            Annotation::new("deprecated").arg("use /v2/users").into(),
+            Annotation::new("internal").into(),
+            Annotation::new("auth").arg("bearer").into(),
+            Annotation::new("tag").arg("billing").arg("users").into(),
+            Annotation::new("timeout").arg("5s").into(),
+        ];
+        let get = &document.endpoints[0];
+        assert_eq!(annotations, get.annotations);
+        assert_eq!(Some(&Annotation::new("auth").arg("bearer")), get.annotation("auth"));
+        assert_eq!((2, 1), (get.annotations[2].span.line, get.annotations[2].span.col));
+
+        let errors = AnnotationRegistry::builtin().validate(&document).unwrap_err();
+        assert_eq!(1, errors.len());
+        assert!(matches!(errors[0], ParseError::UnknownAnnotation { ref name, span } if name == "owner" && span.line == 6));
+
+        let registry = AnnotationRegistry::builtin().register("owner", |_| Ok(()));
+        assert!(registry.validate(&document).is_ok());
+
+        let document = EndpointParser::parse_document("@timeout(soon) GET /a")?;
+        let errors = AnnotationRegistry::builtin().validate(&document).unwrap_err();
+        assert!(matches!(errors[0], ParseError::InvalidAnnotation { ref name, .. } if name == "timeout"));
+
+        let (document, errors) = EndpointParser::parse_document_recovering("GET /a ->\n@tag(a)\nGET /b");
+        assert_eq!(1, errors.len());
+        assert_eq!(Some(&Annotation::new("tag").arg("a")), document.endpoints[0].annotation("tag"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_comments() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "/// Lists all users.
+///
+/// Deleted users are left out.
+@tag(users)
+GET /users -> List<User>
+
+// not a doc comment
+//// neither is this
+GET /health
+/// dangling, documents nothing
+
+/// A user of the system.
+type User { id: long }
+   /// Sort direction.
+   enum Order { asc, desc }
+/// the end",
+        )?;
+        assert_eq!(
+            Some("Lists all users.\n\nDeleted users are left out."),
+            document.endpoints[0].docs.as_deref()
+        );
+        assert_eq!(Some(&Annotation::new("tag").arg("users")), document.endpoints[0].annotation("tag"));
+        assert_eq!(None, document.endpoints[1].docs);
+        assert_eq!(Some("A user of the system."), document.types[0].docs.as_deref());
+        assert_eq!(Some("Sort direction."), document.enums[0].docs.as_deref());
+
+        let (document, errors) =
+            EndpointParser::parse_document_recovering("GET /a ->\n/// Second.\nGET /b");
+        assert_eq!(1, errors.len());
+        assert_eq!(Some("Second."), document.endpoints[0].docs.as_deref());
+
+        // the docs of a broken endpoint are part of it, not an error of their own
+        let (document, errors) =
+            EndpointParser::parse_document_recovering("/// doc\nGET /a/{\nGET /b");
+        assert_eq!(1, errors.len(), "{errors:?}");
+        assert_eq!((2, 9), (errors[0].span().line, errors[0].span().col));
+        assert_eq!(1, document.endpoints.len());
+
+        // stray docs inside a body are comments, not fields, values or items
+        let document = EndpointParser::parse_document(
+            "type T {\n  /// the id\n  id: long\n}
+enum E {\n  /// first\n  a\n}
+service S /s {\n  GET /a\n  /// dangling\n}",
+        )?;
+        assert_eq!(1, document.types[0].fields.len());
+        assert_eq!(vec!["a"], document.enums[0].values().collect::<Vec<_>>());
+        assert_eq!(1, document.services[0].endpoints.len());
+        let err = EndpointParser::parse_document("enum E {\n  /// first\n  a\n}\nGET /a?e:E=\"/// first\"");
+        assert!(matches!(err, Err(ParseError::InvalidDefault { .. })), "{err:?}");
+
+        let document = EndpointParser::parse_document(
+            "\t/// A type.\n\t/// Indented by tabs.\n\ttype T {}\nservice S /s {\n\t/// An endpoint.\n\tGET /a\n}",
+        )?;
+        assert_eq!(Some("A type.\nIndented by tabs."), document.types[0].docs.as_deref());
+        assert_eq!(Some("An endpoint."), document.services[0].endpoints[0].docs.as_deref());
+        Ok(())
+    }
+
+    #[test]
+    fn test_services() -> anyhow::Result<()> {
+        let document = EndpointParser::parse_document(
+            "GET /health
+/// Invoices and payments.
+service Billing /api/v2/billing {
+    GET /invoices -> List<Invoice>
+    // nested services add to the base
+    service Payments /payments/{currency:string} {
+        POST /refunds/{id:uuid}
+    }
+}
+service Empty /empty {}
+type Invoice {}",
+        )?;
+        assert_eq!(1, document.endpoints.len());
+        assert_eq!(2, document.services.len());
+
+        let billing = document.service("Billing").unwrap();
+        assert_eq!(Some("Invoices and payments."), billing.docs.as_deref());
+        let path: Vec<Spanned<Path>> = vec![
+            Path::Segment("api".to_owned()).into(),
+            Path::Segment("v2".to_owned()).into(),
+            Path::Segment("billing".to_owned()).into(),
+            Path::Segment("invoices".to_owned()).into(),
+        ];
+        assert_eq!(path, billing.endpoints[0].path);
+
+        let payments = document.service("Payments").unwrap();
+        assert_eq!(5, payments.base.len());
+        let path: Vec<Spanned<Path>> = vec![
+            Path::Segment("api".to_owned()).into(),
+            Path::Segment("v2".to_owned()).into(),
+            Path::Segment("billing".to_owned()).into(),
+            Path::Segment("payments".to_owned()).into(),
+            Path::Variable(Variable::new("currency", VariableType::String)).into(),
+            Path::Segment("refunds".to_owned()).into(),
+            Path::Variable(Variable::new("id", VariableType::Uuid)).into(),
+        ];
+        assert_eq!(path, payments.endpoints[0].path);
+        assert_eq!(3, document.all_endpoints().count());
+        assert_eq!(2, billing.all_endpoints().count());
+
+        let err = EndpointParser::parse_document("service A /a { GET /b -> B }").unwrap_err();
+        assert!(matches!(err, ParseError::UndeclaredType { ref name, .. } if name == "B"));
+
+        let err = EndpointParser::parse_document("service A /a {}\nservice A /b {}").unwrap_err();
+        assert!(matches!(err, ParseError::Duplicate { ref name, span } if name == "A" && span.line == 2));
+
+        // the base and the path of an endpoint share their variable names
+        // with the query
+        for input in [
+            "service S /a/{id:int} { GET /b/{id:int} }",
+            "GET /a/{id:int}?id:int",
+            "GET /a?x:int&x:string",
+        ] {
+            let err = EndpointParser::parse_document(input).unwrap_err();
+            assert!(matches!(err, ParseError::Duplicate { ref name, .. } if name == "id" || name == "x"), "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_constraints() -> anyhow::Result<()> {
+        let endpoint = EndpointParser::parse_endpoint(
+            "GET /orders/{id:long(1..)}?size:int(1..=100)=20&code?:string(len=3, pattern=\"[A-Z]+\")&ids:[u32(..10);csv]&ratio:double(0..1.5)",
+        )?;
+        let Path::Variable(id) = &endpoint.path[1].node else {
+            panic!("expected a variable")
+        };
+        let range = |min, max, exclusive_max| Range { min, max, exclusive_max };
+        assert_eq!(Some(range(Some(Literal::Integer(1)), None, true)), id.constraints.range);
+
+        let size = &endpoint.query_params[0];
+        assert_eq!(
+            Some(range(Some(Literal::Integer(1)), Some(Literal::Integer(100)), false)),
+            size.constraints.range
+        );
+        assert_eq!(Some(Literal::Integer(20)), size.default.clone().map(|v| v.node));
+
+        let code = &endpoint.query_params[1];
+        let length = Range { min: Some(3), max: Some(3), exclusive_max: false };
+        assert_eq!(Some(length), code.constraints.length);
+        assert_eq!(Some("[A-Z]+"), code.constraints.pattern.as_ref().map(|v| v.as_str()));
+        assert!(code.constraints.admits(&Literal::String("EUR".to_owned())));
+        assert!(!code.constraints.admits(&Literal::String("eur".to_owned())));
+
+        let digits = EndpointParser::parse_endpoint("GET /a?code:string(pattern=\"\\d{3}\")")?;
+        let digits = &digits.query_params[0].constraints;
+        assert!(digits.admits(&Literal::String("123".to_owned())));
+        assert!(!digits.admits(&Literal::String("ddd".to_owned())));
+
+        let ids = &endpoint.query_params[2];
+        assert_eq!(Some(ListStyle::CommaSeparated), ids.list);
+        assert_eq!(Some(range(None, Some(Literal::Integer(10)), true)), ids.constraints.range);
+        assert_eq!(
+            Some(range(Some(Literal::Float(0.0)), Some(Literal::Float(1.5)), true)),
+            endpoint.query_params[3].constraints.range
+        );
+
+        let invalid = [
+            "GET /a?x:int(5..=1)",
+            "GET /a?x:int(..)",
+            "GET /a?x:byte(0..=300)",
+            "GET /a?x:string(1..2)",
+            "GET /a?x:int(len=2)",
+            "GET /a?x:string(pattern=\"(\")",
+            "GET /a?x:string(len=1, len=2)",
+        ];
+        for input in invalid {
+            let err = EndpointParser::parse_endpoint(input).unwrap_err();
+            assert!(matches!(err, ParseError::InvalidConstraint { .. }), "{input}: {err:?}");
+        }
+
+        let reasons = [
+            ("GET /a?x:int(1.5..)", "`1.5` is not a valid int"),
+            ("GET /a?x:byte(0..=300)", "`300` is out of range"),
+            ("GET /a?x:string(len=..1.5)", "`1.5` is not a length"),
+        ];
+        for (input, expected) in reasons {
+            let err = EndpointParser::parse_endpoint(input).unwrap_err();
+            assert!(matches!(err, ParseError::InvalidConstraint { ref reason, .. } if reason == expected), "{input}: {err:?}");
+        }
+
+        let err = EndpointParser::parse_endpoint("GET /a?x:int(1..=100)=0").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDefault { ref expected, .. } if expected == "int(1..=100)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_catch_all() -> anyhow::Result<()> {
+        let endpoint = EndpointParser::parse_endpoint("GET /files/{*path:string} -> File")?;
+        assert_eq!(
+            vec![
+                Path::Segment("files".to_owned()),
+                Path::CatchAll(Variable::new("path", VariableType::String)),
+            ],
+            endpoint.path.iter().map(|v| v.node.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("path"), endpoint.path[1].variables().next().map(|v| v.name.as_str()));
+
+        let document = EndpointParser::parse_document(
+            "service Files /files {\n  GET /{*path:string}\n}",
+        )?;
+        assert!(document.resolve().is_ok());
+
+        let invalid = [
+            "GET /files/{*path:string}/meta",
+            "GET /files/{*path:int}",
+            "service Files /files/{*path:string} {\n  GET /meta\n}",
+        ];
+        for input in invalid {
+            let err = EndpointParser::parse_document(input).unwrap_err();
+            assert!(matches!(err, ParseError::InvalidCatchAll { .. }), "{input}: {err:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_literal_segments() -> anyhow::Result<()> {
+        let segments = |input: &str| -> anyhow::Result<Vec<Path>> {
+            let endpoint = EndpointParser::parse_endpoint(input)?;
+            Ok(endpoint.path.into_iter().map(|v| v.node).collect())
+        };
+        let segment = |text: &str| Path::Segment(text.to_owned());
+
+        assert_eq!(
+            vec![segment("v1.2"), segment("health-check"), segment("2fa")],
+            segments("GET /v1.2/health-check/2fa")?
+        );
+        assert_eq!(
+            vec![
+                segment("user_profile"),
+                segment("~me"),
+                segment("caf%C3%A9"),
+                segment("..."),
+            ],
+            segments("GET /user_profile/~me/caf%C3%A9/...")?
+        );
+        assert_eq!(vec![Path::TrailingSlash], segments("GET /")?);
+        assert_eq!(vec![segment("a"), Path::TrailingSlash], segments("GET /a/")?);
+        assert_ne!(segments("GET /a/")?, segments("GET /a")?);
+
+        let endpoint = EndpointParser::parse_endpoint("GET /?page:int RQ -> RS")?;
+        assert_eq!(vec![Spanned::from(Path::TrailingSlash)], endpoint.path);
+        assert_eq!(1, endpoint.query_params.len());
+
+        // a base ending in `/` prefixes paths like one without
+        let document =
+            EndpointParser::parse_document("service Api /api/ {\n  GET /users\n  GET /\n}")?;
+        let service = document.service("Api").unwrap();
+        let paths: Vec<Vec<Path>> = service
+            .endpoints
+            .iter()
+            .map(|v| v.path.iter().map(|v| v.node.clone()).collect())
+            .collect();
+        assert_eq!(vec![Spanned::from(segment("api"))], service.base);
+        assert_eq!(
+            vec![
+                vec![segment("api"), segment("users")],
+                vec![segment("api"), Path::TrailingSlash],
+            ],
+            paths
+        );
+
+        let invalid = [
+            "GET /a%2",
+            "GET /a//b",
+            "GET /a b",
+            "GET /../etc",
+            "GET /a/./b",
+            "GET /a/..",
+        ];
+        for input in invalid {
+            assert!(EndpointParser::parse_document(input).is_err(), "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_segments() -> anyhow::Result<()> {
+        let endpoint =
+            EndpointParser::parse_endpoint("GET /reports/{id:long}.{format:string} -> Report")?;
+        assert_eq!(
+            Path::Mixed(vec![
+                PathPart::Variable(Variable::new("id", VariableType::Long)),
+                PathPart::Literal(".".to_owned()),
+                PathPart::Variable(Variable::new("format", VariableType::String)),
+            ]),
+            endpoint.path[1].node
+        );
+        let names: Vec<&str> = endpoint.path[1].variables().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["id", "format"], names);
+
+        let endpoint = EndpointParser::parse_endpoint("GET /items/v{version:int}/tags")?;
+        assert_eq!(
+            vec![
+                Path::Segment("items".to_owned()),
+                Path::Mixed(vec![
+                    PathPart::Literal("v".to_owned()),
+                    PathPart::Variable(Variable::new("version", VariableType::Int)),
+                ]),
+                Path::Segment("tags".to_owned()),
+            ],
+            endpoint.path.iter().map(|v| v.node.clone()).collect::<Vec<_>>()
+        );
+
+        EndpointParser::parse_document("enum Format { csv, pdf }\nGET /a/{id:long}.{format:Format}")?;
+        let err = EndpointParser::parse_document("GET /a/{id:long}.{format:Format}").unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportType { ref name, .. } if name == "Format"));
+
+        // adjacent variables cannot be told apart
+        assert!(EndpointParser::parse_document("GET /a/{x:int}{y:int}").is_err());
+
+        // the `{` opening a service body does not make the base mixed
+        let document = EndpointParser::parse_document("service A /a{GET\t/}")?;
+        assert_eq!(Path::Segment("a".to_owned()), document.services[0].base[0].node);
+        assert!(EndpointParser::parse_document("service A /..{GET /}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_layout() -> anyhow::Result<()> {
+        let single = EndpointParser::parse_endpoint(
+            "GET /users?page:int=1&size:int(1..=100) [X-Tenant:string] Filter -> 200: UserList | 404: NotFound",
+        )?;
+        let inputs = [
+            "GET\t/users?page:int=1&size:int(1..=100)\t[X-Tenant:string]\tFilter->200: UserList|404: NotFound",
+            "GET  /users ? page : int = 1 & size : int(1..=100) [ X-Tenant:string ] Filter  ->  200: UserList | 404: NotFound",
+            "GET /users
+    ?page:int=1
+    &size:int(1..=100)
+    [X-Tenant:string]
+    Filter
+    -> 200: UserList
+     | 404: NotFound",
+        ];
+        for input in inputs {
+            assert_eq!(single, EndpointParser::parse_endpoint(input)?, "{input}");
+        }
+
+        // comments go anywhere whitespace does, `as` may start a line
+        let input = "GET /users // all of them
+    ?page:int=1 /* first page */
+    &size:int(1..=100)
+    [X-Tenant:string] // required
+    Filter
+      as form
+    -> 200: UserList
+     | 404: NotFound";
+        let endpoint = EndpointParser::parse_endpoint(input)?;
+        assert_eq!(Some(MediaType::Form), endpoint.request_media_type.as_deref().cloned());
+        assert_eq!(
+            "page:int=1",
+            &input[endpoint.query_params[0].span.start..endpoint.query_params[0].span.end]
+        );
+        assert_eq!(
+            "X-Tenant:string",
+            &input[endpoint.headers[0].span.start..endpoint.headers[0].span.end]
+        );
+
+        // whitespace skipped after the last token is not part of a span
+        let endpoint = EndpointParser::parse_endpoint("GET /a\n  ?page:int\n  &size:int\n")?;
+        assert_eq!(Span { start: 10, end: 18, line: 2, col: 4 }, endpoint.query_params[0].span);
+
+        let document = EndpointParser::parse_document(
+            "GET /a\n  ?page:int\n  -> RS\nGET /b\n\ntype RS {}\n",
+        )?;
+        assert_eq!(2, document.endpoints.len());
+        assert!(EndpointParser::parse_document("GET/a").is_err());
+
+        // a one word request type before a comment is not a method
+        let document = EndpointParser::parse_document(
+            "type A {}\ntype Get {}\nGET /a A\n/* x */\nGET /b\nPOST /c Get\n// note",
+        )?;
+        assert_eq!(3, document.endpoints.len());
+        assert_eq!(Some(TypeExpr::named("Get")), document.endpoints[2].request_type.clone().map(|v| v.node));
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> anyhow::Result<()> {
+        let endpoint = EndpointParser::parse_endpoint(
+            "/// Lists users.\n@tag(users, \"public api\")\nget  /users/{org:string}/v{v:int}.json?page:int(1..)=1&ids?:[long; csv]  [X-Tenant:string(len=3)] Filter as form ->  200: List<User>|404: NotFound as xml [X-Total:long]",
+        )?;
+        assert_eq!(
+            "/// Lists users.\n@tag(users, \"public api\")\nGET /users/{org:string}/v{v:int}.json?page:int(1..)=1&ids?:[long; csv] [X-Tenant:string(len=3)] Filter as form -> 200: List<User> | 404: NotFound as xml [X-Total:long]",
+            endpoint.to_string()
+        );
+        assert_eq!(
+            "q:string(pattern=\"\\\\d+\")=\"a \\\"b\\\"\"",
+            Variable::new("q", VariableType::String)
+                .with_constraints(Constraints {
+                    pattern: Some(Pattern::new("\\d+")?),
+                    ..Default::default()
+                })
+                .with_default(Literal::String("a \"b\"".to_owned()))
+                .to_string()
+        );
+
+        let input = "
+            service Api /api/ {
+              GET / -> Index
+              service Files /files { GET /{*path:string} -> File }
+            }
+            import \"common.idl\"
+            type Page<T> { items: List<T>, next?: string }
+            enum Order { asc, desc }
+            PURGE /cache
+        ";
+        let document = EndpointParser::parse_rule::<Document>(Rule::document, input)?;
+        let expected = r#"import "common.idl"
+
+enum Order {
+    asc
+    desc
+}
+
+type Page<T> {
+    items: List<T>
+    next?: string
+}
+
+PURGE /cache
+
+service Api /api {
+    GET / -> Index
+
+    service Files /files {
+        GET /{*path:string} -> File
+    }
+}
+"#;
+        assert_eq!(expected, document.to_string());
+        assert_eq!(document, EndpointParser::parse_rule(Rule::document, expected)?);
+
+        // a path built by hand that does not start with the base is written in full
+        let service = Service {
+            docs: None,
+            name: "S".to_owned().into(),
+            base: vec![Path::Segment("a".to_owned()).into(), Path::Segment("b".to_owned()).into()],
+            endpoints: vec![EndpointParser::parse_endpoint("GET /x")?, EndpointParser::parse_endpoint("GET /a/b/c")?],
+            services: Vec::new(),
+        };
+        assert_eq!("service S /a/b {\n    GET /x\n    GET /c\n}", service.to_string());
+        Ok(())
+    }
 }
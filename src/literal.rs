@@ -0,0 +1,307 @@
+use pest::iterators::Pair;
+
+use crate::{Children, ParseError, Rule, Spanned, VariableType};
+
+/// A literal value written in IDL source, e.g. the default of a query
+/// parameter. The variant always matches the type it was checked against.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Literal {
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    /// Decimal kept as written so no precision is lost.
+    Decimal(String),
+    /// Text of any textual type: string, uuid, date, email, ...
+    String(String),
+}
+
+impl VariableType {
+    /// Parses `text` as a value of this type, e.g. a default value or a raw
+    /// query string value a request validator has to check.
+    pub fn parse_literal(&self, text: &str) -> Option<Literal> {
+        match self {
+            Self::Bool => match text {
+                "true" => Some(Literal::Bool(true)),
+                "false" => Some(Literal::Bool(false)),
+                _ => None,
+            },
+            Self::Float | Self::Double => text
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && is_number(text))
+                .map(Literal::Float),
+            Self::Decimal => is_number(text).then(|| Literal::Decimal(text.to_owned())),
+            Self::String => Some(Literal::String(text.to_owned())),
+            Self::Uuid => is_uuid(text).then(|| Literal::String(text.to_owned())),
+            Self::Date => is_date(text).then(|| Literal::String(text.to_owned())),
+            Self::DateTime => is_datetime(text).then(|| Literal::String(text.to_owned())),
+            Self::Duration => is_duration(text).then(|| Literal::String(text.to_owned())),
+            Self::Email => is_email(text).then(|| Literal::String(text.to_owned())),
+            Self::Url => is_url(text).then(|| Literal::String(text.to_owned())),
+            Self::Binary => is_base64(text).then(|| Literal::String(text.to_owned())),
+            // whether the value is one of the enum's is up to `Document::resolve`
+            Self::Enum(_) => (!text.is_empty()).then(|| Literal::String(text.to_owned())),
+            integer => {
+                let (min, max) = integer.integer_range()?;
+                text.parse::<i128>()
+                    .ok()
+                    .filter(|v| (min..=max).contains(v))
+                    .map(Literal::Integer)
+            }
+        }
+    }
+
+    /// Types whose values are written as text, so they may be quoted.
+    pub fn is_textual(&self) -> bool {
+        !(self.is_numeric() || *self == Self::Bool)
+    }
+}
+
+impl Literal {
+    /// Converts a `literal` pair into a value of `variable_type`.
+    pub(crate) fn of_type(
+        value: Pair<'_, Rule>,
+        variable_type: &VariableType,
+    ) -> Result<Spanned<Self>, ParseError> {
+        if Rule::literal != value.as_rule() {
+            return Err(ParseError::unexpect_rule(Rule::literal, &value));
+        }
+        let span = value.as_span().into();
+        let pair = Children::of(value).next_pair()?;
+        let literal = match pair.as_rule() {
+            Rule::string_literal if variable_type.is_textual() => {
+                variable_type.parse_literal(&unescape(pair.as_str()))
+            }
+            Rule::string_literal => None,
+            _ => variable_type.parse_literal(pair.as_str()),
+        };
+
+        literal
+            .map(|v| Spanned::new(v, span))
+            .ok_or_else(|| ParseError::InvalidDefault {
+                value: pair.as_str().to_owned(),
+                expected: variable_type.as_str().to_owned(),
+                span,
+            })
+    }
+}
+
+/// Strips the quotes of a string literal and resolves its escapes.
+pub(crate) fn unescape(quoted: &str) -> String {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(quoted);
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c @ ('"' | '\\')) => out.push(c),
+            // kept as written, `pattern="\d+"` means a regex escape
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn is_digits(text: &str, len: usize) -> bool {
+    text.len() == len && text.bytes().all(|v| v.is_ascii_digit())
+}
+
+fn is_number(text: &str) -> bool {
+    let text = text.strip_prefix('-').unwrap_or(text);
+    let (int, frac) = text.split_once('.').unwrap_or((text, "0"));
+    !int.is_empty()
+        && !frac.is_empty()
+        && int.bytes().all(|v| v.is_ascii_digit())
+        && frac.bytes().all(|v| v.is_ascii_digit())
+}
+
+fn is_uuid(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn is_date(text: &str) -> bool {
+    let mut parts = text.split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    is_digits(year, 4)
+        && is_digits(month, 2)
+        && is_digits(day, 2)
+        && (1..=12).contains(&month.parse::<u8>().unwrap_or(0))
+        && (1..=31).contains(&day.parse::<u8>().unwrap_or(0))
+}
+
+fn is_time(text: &str) -> bool {
+    let (time, offset) = match text.find(['Z', 'z', '+', '-']) {
+        Some(i) => text.split_at(i),
+        None => return false,
+    };
+    let time = time.split_once('.').map_or(time, |(time, frac)| {
+        if !frac.is_empty() && frac.bytes().all(|v| v.is_ascii_digit()) {
+            time
+        } else {
+            ""
+        }
+    });
+    let valid_offset = match offset {
+        "Z" | "z" => true,
+        offset => {
+            let offset = &offset[1..];
+            offset.len() == 5
+                && offset.as_bytes()[2] == b':'
+                && is_digits(&offset[..2], 2)
+                && is_digits(&offset[3..], 2)
+        }
+    };
+    let mut parts = time.split(':');
+    valid_offset
+        && parts.next().is_some_and(|v| is_digits(v, 2))
+        && parts.next().is_some_and(|v| is_digits(v, 2))
+        && parts.next().is_some_and(|v| is_digits(v, 2))
+        && parts.next().is_none()
+}
+
+fn is_datetime(text: &str) -> bool {
+    match text.split_once(['T', 't']) {
+        Some((date, time)) => is_date(date) && is_time(time),
+        None => false,
+    }
+}
+
+fn is_duration(text: &str) -> bool {
+    let Some(rest) = text.strip_prefix('P') else {
+        return false;
+    };
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+    let valid = |part: &str, units: &str| {
+        let mut number = false;
+        part.chars().all(|c| {
+            if c.is_ascii_digit() || (c == '.' && number) {
+                number = true;
+                true
+            } else {
+                let unit = number && units.contains(c);
+                number = false;
+                unit
+            }
+        }) && !number
+    };
+    !rest.is_empty() && !rest.ends_with('T') && valid(date, "YMWD") && valid(time, "HMS")
+}
+
+fn is_email(text: &str) -> bool {
+    match text.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.is_empty()
+                && !domain.contains('@')
+                && !text.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn is_url(text: &str) -> bool {
+    match text.split_once(':') {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && !rest.is_empty()
+                && !text.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn is_base64(text: &str) -> bool {
+    let data = text.trim_end_matches('=');
+    text.len() - data.len() <= 2
+        && data
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/-_".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_literal() {
+        let cases = [
+            (VariableType::Bool, "true", Some(Literal::Bool(true))),
+            (VariableType::Bool, "yes", None),
+            (VariableType::Byte, "-128", Some(Literal::Integer(-128))),
+            (VariableType::Byte, "128", None),
+            (VariableType::U8, "-1", None),
+            (VariableType::Int, "1.5", None),
+            (VariableType::Double, "1.5", Some(Literal::Float(1.5))),
+            (VariableType::Double, "inf", None),
+            (
+                VariableType::Decimal,
+                "-12.50",
+                Some(Literal::Decimal("-12.50".to_owned())),
+            ),
+            (VariableType::Decimal, "1e5", None),
+        ];
+        for (variable_type, text, expected) in cases {
+            assert_eq!(expected, variable_type.parse_literal(text), "{text}");
+        }
+
+        let text = [
+            (
+                VariableType::Uuid,
+                "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                true,
+            ),
+            (VariableType::Uuid, "67e55044", false),
+            (VariableType::Date, "2023-08-01", true),
+            (VariableType::Date, "2023-13-01", false),
+            (VariableType::DateTime, "2023-08-01T12:00:00Z", true),
+            (VariableType::DateTime, "2023-08-01T12:00:00.25+02:00", true),
+            (VariableType::DateTime, "2023-08-01", false),
+            (VariableType::Duration, "PT5M", true),
+            (VariableType::Duration, "P1Y2M3DT4H5M6.5S", true),
+            (VariableType::Duration, "P", false),
+            (VariableType::Duration, "5s", false),
+            (VariableType::Email, "user@example.com", true),
+            (VariableType::Email, "user", false),
+            (VariableType::Url, "https://example.com/a", true),
+            (VariableType::Url, "example", false),
+            (VariableType::Binary, "aGVsbG8=", true),
+            (VariableType::Binary, "a b", false),
+        ];
+        for (variable_type, value, valid) in text {
+            assert_eq!(
+                valid,
+                variable_type.parse_literal(value).is_some(),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!("a \"b\"\n\\", unescape(r#""a \"b\"\n\\""#));
+        assert_eq!("\\d{3}", unescape(r#""\d{3}""#));
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    Document, EndpointParser, FileId, ParseError, Rule, SourceMap, Span, Spanned, TypeDef,
+};
+
+/// Where a [`Loader`] reads files from.
+pub trait FileSystem {
+    fn read(&self, path: &Path) -> io::Result<String>;
+}
+
+/// The file system of the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Files kept in memory, for tests and for specs that do not live on disk.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn file(mut self, path: impl AsRef<Path>, source: impl Into<String>) -> Self {
+        self.files.insert(normalize(path.as_ref()), source.into());
+        self
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+    }
+}
+
+/// A [`ParseError`] together with the file it was found in.
+#[derive(thiserror::Error, Debug)]
+#[error("{error}")]
+pub struct FileError {
+    pub file: FileId,
+    pub error: ParseError,
+}
+
+/// Everything that went wrong loading a spec, with the files to render the
+/// errors against.
+#[derive(thiserror::Error, Debug)]
+#[error("failed to load the spec, {} error(s)", .errors.len())]
+pub struct LoadError {
+    pub source_map: SourceMap,
+    pub errors: Vec<FileError>,
+}
+
+impl LoadError {
+    /// Every error rendered with [`SourceMap::render`].
+    pub fn render(&self) -> String {
+        self.errors
+            .iter()
+            .map(|v| self.source_map.render(v))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A parsed file and the files it imports.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Module {
+    pub file: FileId,
+    pub document: Document,
+    /// In the order of the `import` statements.
+    pub imports: Vec<FileId>,
+}
+
+/// A spec loaded from an entry file and everything it imports.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub source_map: SourceMap,
+    /// One module per file, the entry file first.
+    pub modules: Vec<Module>,
+}
+
+impl Project {
+    pub fn module(&self, file: FileId) -> Option<&Module> {
+        self.modules.iter().find(|v| v.file == file)
+    }
+
+    /// The module of the file the project was loaded from.
+    pub fn entry(&self) -> &Module {
+        &self.modules[0]
+    }
+
+    /// Looks up a type declared in any file, with the file declaring it.
+    pub fn type_def(&self, name: &str) -> Option<(FileId, &TypeDef)> {
+        self.modules
+            .iter()
+            .find_map(|v| Some((v.file, v.document.type_def(name)?)))
+    }
+}
+
+/// Loads a spec spread over several files.
+///
+/// `import` paths are relative to the importing file. A file sees the
+/// declarations of the files it imports directly, and every file is parsed
+/// once however often it is imported.
+///
+/// ```
+/// # use idl_parser::{Loader, MemoryFileSystem};
+/// let fs = MemoryFileSystem::new()
+///     .file("api/users.idl", "import \"../common/types.idl\"\nGET /users/{id:long} -> User")
+///     .file("common/types.idl", "type User { id: long }");
+/// let project = Loader::new(fs).load("api/users.idl")?;
+/// assert_eq!(2, project.modules.len());
+/// # Ok::<_, idl_parser::LoadError>(())
+/// ```
+pub struct Loader<F> {
+    fs: F,
+}
+
+impl<F: FileSystem> Loader<F> {
+    pub fn new(fs: F) -> Self {
+        Self { fs }
+    }
+
+    pub fn load(&self, entry: impl AsRef<Path>) -> Result<Project, LoadError> {
+        let mut state = State::default();
+        let entry = normalize(entry.as_ref());
+        match self.fs.read(&entry) {
+            Ok(source) => {
+                self.load_file(&mut state, entry, source);
+            }
+            Err(err) => {
+                let file = state.source_map.add(&entry, "");
+                state.errors.push(FileError {
+                    file,
+                    error: ParseError::Import {
+                        path: entry.display().to_string(),
+                        reason: err.to_string(),
+                        span: Span::default(),
+                    },
+                });
+            }
+        }
+
+        let documents: HashMap<FileId, &Document> = state
+            .modules
+            .iter()
+            .map(|v| (v.file, &v.document))
+            .collect();
+        for module in &state.modules {
+            let imports: Vec<&Document> = module.imports.iter().map(|v| documents[v]).collect();
+            if let Err(errors) = module.document.resolve_with(&imports) {
+                let file = module.file;
+                // names from an import that failed to load are missing, an
+                // error for each use would only repeat that failure
+                let broken = state.broken.contains(&file);
+                let errors = errors.into_iter().filter(|v| {
+                    !broken
+                        || !matches!(
+                            v,
+                            ParseError::UndeclaredType { .. } | ParseError::UnsupportType { .. }
+                        )
+                });
+                state
+                    .errors
+                    .extend(errors.map(|error| FileError { file, error }));
+            }
+        }
+
+        let State {
+            source_map,
+            mut modules,
+            errors,
+            ..
+        } = state;
+        if !errors.is_empty() {
+            return Err(LoadError { source_map, errors });
+        }
+        modules.sort_by_key(|v| v.file);
+        Ok(Project {
+            source_map,
+            modules,
+        })
+    }
+
+    /// Parses `source` and loads its imports depth first, returning the id
+    /// of the file, or `None` if it does not parse.
+    fn load_file(&self, state: &mut State, path: PathBuf, source: String) -> Option<FileId> {
+        let file = state.source_map.add(&path, source);
+        let document: Result<Document, ParseError> =
+            EndpointParser::parse_rule(Rule::document, state.source_map.source(file));
+        let document = match document {
+            Ok(document) => document,
+            Err(error) => {
+                state.errors.push(FileError { file, error });
+                return None;
+            }
+        };
+
+        state.loading.push((path.clone(), file));
+        let mut imports = Vec::new();
+        for import in &document.imports {
+            match self.load_import(state, &path, import) {
+                Some(imported) => imports.push(imported),
+                None => {
+                    state.broken.insert(file);
+                }
+            }
+        }
+        state.loading.pop();
+
+        state.modules.push(Module {
+            file,
+            document,
+            imports,
+        });
+        Some(file)
+    }
+
+    fn load_import(
+        &self,
+        state: &mut State,
+        importer: &Path,
+        import: &Spanned<String>,
+    ) -> Option<FileId> {
+        let importer_file = state.loading.last().map(|v| v.1).unwrap_or_default();
+        let path = normalize(&importer.parent().unwrap_or(Path::new("")).join(&**import));
+
+        if let Some(i) = state.loading.iter().position(|v| v.0 == path) {
+            let cycle = state.loading[i..]
+                .iter()
+                .map(|v| v.0.display().to_string())
+                .chain([path.display().to_string()])
+                .collect();
+            state.errors.push(FileError {
+                file: importer_file,
+                error: ParseError::ImportCycle {
+                    path: import.node.clone(),
+                    cycle,
+                    span: import.span,
+                },
+            });
+            return None;
+        }
+        if let Some(file) = state.source_map.find(&path) {
+            // already loaded, or failed to parse and reported already
+            return state.modules.iter().any(|v| v.file == file).then_some(file);
+        }
+
+        match self.fs.read(&path) {
+            Ok(source) => self.load_file(state, path, source),
+            Err(err) => {
+                state.errors.push(FileError {
+                    file: importer_file,
+                    error: ParseError::Import {
+                        path: import.node.clone(),
+                        reason: err.to_string(),
+                        span: import.span,
+                    },
+                });
+                None
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    source_map: SourceMap,
+    modules: Vec<Module>,
+    errors: Vec<FileError>,
+    /// The files whose imports are being loaded, the innermost last.
+    loading: Vec<(PathBuf, FileId)>,
+    /// The files with an import that failed to load.
+    broken: HashSet<FileId>,
+}
+
+/// Resolves `.` and `..` without touching the file system, which may not be
+/// a real one.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fs() -> MemoryFileSystem {
+        MemoryFileSystem::new()
+            .file(
+                "api/main.idl",
+                "import \"users.idl\"\nimport \"../common/types.idl\"\nGET /health -> Status",
+            )
+            .file(
+                "api/users.idl",
+                "import \"../common/types.idl\"\nGET /users/{id:long} -> User",
+            )
+            .file("common/types.idl", "type User { id: long }\ntype Status {}")
+    }
+
+    #[test]
+    fn test_load() -> Result<(), LoadError> {
+        let project = Loader::new(fs()).load("./api/main.idl")?;
+        assert_eq!(3, project.modules.len());
+        assert_eq!(
+            Path::new("api/main.idl"),
+            project.source_map.path(project.entry().file)
+        );
+
+        let users = project.source_map.find(Path::new("api/users.idl")).unwrap();
+        let types = project
+            .source_map
+            .find(Path::new("common/types.idl"))
+            .unwrap();
+        let imports: Vec<Spanned<String>> = vec![
+            "users.idl".to_owned().into(),
+            "../common/types.idl".to_owned().into(),
+        ];
+        assert_eq!(imports, project.entry().document.imports);
+        assert_eq!(vec![users, types], project.entry().imports);
+        assert_eq!(vec![types], project.module(users).unwrap().imports);
+        assert_eq!(Some(types), project.type_def("User").map(|v| v.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let fs = fs()
+            .file("api/bad.idl", "import \"users.idl\"\nGET /a -> User")
+            .file("cycle/a.idl", "import \"b.idl\"")
+            .file("cycle/b.idl", "\nimport \"./a.idl\"");
+
+        let err = Loader::new(fs.clone()).load("api/bad.idl").unwrap_err();
+        assert_eq!(1, err.errors.len());
+        assert_eq!(
+            Path::new("api/bad.idl"),
+            err.source_map.path(err.errors[0].file)
+        );
+        // types are only visible to the files importing them directly
+        assert!(err.render().starts_with(
+            "error: undeclared type `User` at 2:11\n --> api/bad.idl:2:11\n  |\n2 | GET /a -> User\n"
+        ));
+
+        let err = Loader::new(fs.clone()).load("cycle/a.idl").unwrap_err();
+        let FileError { file, error } = &err.errors[0];
+        assert_eq!(Path::new("cycle/b.idl"), err.source_map.path(*file));
+        assert!(
+            matches!(error, ParseError::ImportCycle { cycle, span, .. } if cycle.len() == 3 && span.line == 2),
+            "{error:?}"
+        );
+
+        // a file that does not parse is reported once, not again for every
+        // name its importers miss, other errors of the importers still are
+        let fs = fs
+            .file("api/types.idl", "type User {")
+            .file(
+                "api/a.idl",
+                "import \"types.idl\"\nimport \"b.idl\"\nGET /a -> User",
+            )
+            .file(
+                "api/b.idl",
+                "import \"types.idl\"\nGET /b -> User\ntype B {}\ntype B {}",
+            );
+        let err = Loader::new(fs.clone()).load("api/a.idl").unwrap_err();
+        assert_eq!(2, err.errors.len(), "{}", err.render());
+        assert_eq!(
+            Path::new("api/types.idl"),
+            err.source_map.path(err.errors[0].file)
+        );
+        assert!(matches!(err.errors[1].error, ParseError::Duplicate { .. }));
+
+        let err = Loader::new(fs).load("api/missing.idl").unwrap_err();
+        assert!(matches!(err.errors[0].error, ParseError::Import { .. }));
+    }
+}
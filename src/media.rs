@@ -0,0 +1,111 @@
+use pest::iterators::Pair;
+
+use crate::{ParseError, Rule};
+
+/// The encoding of a request or response body, JSON unless the IDL says
+/// otherwise with `as`, e.g. `Upload as multipart -> Report as csv`.
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub enum MediaType {
+    /// `application/json`
+    #[default]
+    Json,
+    /// `application/x-www-form-urlencoded`
+    Form,
+    /// `multipart/form-data`
+    Multipart,
+    /// `text/csv`
+    Csv,
+    /// `text/plain`
+    Text,
+    /// `application/xml`
+    Xml,
+    /// `application/octet-stream`
+    Binary,
+    /// Any other `type/subtype`, e.g. `application/pdf`.
+    Other(String),
+}
+
+impl MediaType {
+    /// Shorthands accepted after `as`, besides a full `type/subtype`.
+    pub const NAMES: &'static [&'static str] =
+        &["json", "form", "multipart", "csv", "text", "xml", "binary"];
+
+    const KNOWN: [Self; 7] = [
+        Self::Json,
+        Self::Form,
+        Self::Multipart,
+        Self::Csv,
+        Self::Text,
+        Self::Xml,
+        Self::Binary,
+    ];
+
+    /// The value of the `Content-Type` header.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Json => "application/json",
+            Self::Form => "application/x-www-form-urlencoded",
+            Self::Multipart => "multipart/form-data",
+            Self::Csv => "text/csv",
+            Self::Text => "text/plain",
+            Self::Xml => "application/xml",
+            Self::Binary => "application/octet-stream",
+            Self::Other(mime) => mime,
+        }
+    }
+
+    /// The shorthand of a known media type, e.g. `csv` for [`MediaType::Csv`].
+    pub fn shorthand(&self) -> Option<&'static str> {
+        Self::KNOWN
+            .iter()
+            .position(|v| v == self)
+            .map(|i| Self::NAMES[i])
+    }
+
+    /// Looks up a shorthand or a `type/subtype`, known ones by either name.
+    pub fn parse(text: &str) -> Option<Self> {
+        let known = Self::NAMES.iter().zip(Self::KNOWN).find(|(name, known)| {
+            name.eq_ignore_ascii_case(text) || known.as_str().eq_ignore_ascii_case(text)
+        });
+        match known {
+            Some((_, known)) => Some(known),
+            None if text.contains('/') => Some(Self::Other(text.to_ascii_lowercase())),
+            None => None,
+        }
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for MediaType {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::media_type == value.as_rule() {
+            Self::parse(value.as_str()).ok_or_else(|| ParseError::UnsupportMediaType {
+                name: value.as_str().to_owned(),
+                span: value.as_span().into(),
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::media_type, &value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_media_type() {
+        assert_eq!(Some(MediaType::Csv), MediaType::parse("csv"));
+        assert_eq!(
+            Some(MediaType::Multipart),
+            MediaType::parse("multipart/form-data")
+        );
+        assert_eq!(
+            Some(MediaType::Other("application/pdf".to_owned())),
+            MediaType::parse("Application/PDF")
+        );
+        assert_eq!(None, MediaType::parse("yaml"));
+        assert_eq!("application/json", MediaType::default().as_str());
+    }
+}
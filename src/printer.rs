@@ -0,0 +1,372 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::{
+    Annotation, Constraints, Document, Endpoint, EnumDef, Field, ListStyle, Literal, MediaType,
+    Method, Path, PathPart, Range, Service, Spanned, StatusResponse, TypeDef, TypeExpr, Variable,
+    VariableType,
+};
+
+const INDENT: &str = "    ";
+
+/// Formats a document in canonical syntax: imports, enums, types, endpoints
+/// and services in that order, one token of space between the parts of an
+/// endpoint and upper case methods. Parsing the output gives back an equal
+/// document, as long as the paths in every service start with its base.
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let imports = self.imports.iter().map(|v| format!("import {}", quote(v)));
+        let sections = [
+            imports.collect::<Vec<_>>().join("\n"),
+            join(&self.enums, "\n\n"),
+            join(&self.types, "\n\n"),
+            join(&self.endpoints, "\n"),
+            join(&self.services, "\n\n"),
+        ];
+        let sections: Vec<String> = sections.into_iter().filter(|v| !v.is_empty()).collect();
+        if sections.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "{}", sections.join("\n\n"))
+    }
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_endpoint(f, self, &[])
+    }
+}
+
+impl Display for Service {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_service(f, self, &[])
+    }
+}
+
+impl Display for TypeDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_docs(f, &self.docs)?;
+        write!(f, "type {}", self.name.node)?;
+        if !self.params.is_empty() {
+            write!(f, "<{}>", join(&self.params, ", "))?;
+        }
+        write_block(f, self.fields.iter().map(|v| v.to_string()))
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let optional = if self.optional { "?" } else { "" };
+        write!(f, "{}{optional}: {}", self.name, self.field_type.node)
+    }
+}
+
+impl Display for EnumDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_docs(f, &self.docs)?;
+        write!(f, "enum {}", self.name.node)?;
+        write_block(f, self.values().map(str::to_owned))
+    }
+}
+
+impl Display for TypeExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scalar(variable_type) => write!(f, "{variable_type}"),
+            Self::List(item) => write!(f, "List<{}>", item.node),
+            Self::Map(key, value) => write!(f, "Map<{}, {}>", key.node, value.node),
+            Self::Option(inner) => write!(f, "Option<{}>", inner.node),
+            Self::Named(name, args) if args.is_empty() => f.write_str(name),
+            Self::Named(name, args) => write!(f, "{name}<{}>", join(args, ", ")),
+        }
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Display for VariableType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One element of a path, with its leading `/`.
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Segment(text) => write!(f, "/{text}"),
+            Self::Variable(variable) => write!(f, "/{{{variable}}}"),
+            Self::CatchAll(variable) => write!(f, "/{{*{variable}}}"),
+            Self::TrailingSlash => f.write_str("/"),
+            Self::Mixed(parts) => {
+                f.write_str("/")?;
+                parts.iter().try_for_each(|v| match v {
+                    PathPart::Literal(text) => f.write_str(text),
+                    PathPart::Variable(variable) => write!(f, "{{{variable}}}"),
+                })
+            }
+        }
+    }
+}
+
+/// `name?:type(constraints)=default`, as in a query or a header block.
+impl Display for Variable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let optional = if self.optional { "?" } else { "" };
+        let (variable_type, constraints) = (&self.variable_type.node, &self.constraints);
+        write!(f, "{}{optional}:", self.name)?;
+        match self.list {
+            Some(ListStyle::Repeated) => write!(f, "[{variable_type}{constraints}]")?,
+            Some(ListStyle::CommaSeparated) => write!(f, "[{variable_type}{constraints}; csv]")?,
+            None => write!(f, "{variable_type}{constraints}")?,
+        }
+        match &self.default {
+            Some(default) => write!(f, "={}", default.node),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `(1..=100)`, `(len=3, pattern="[A-Z]+")`, nothing if there are none.
+impl Display for Constraints {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut constraints = Vec::new();
+        if let Some(range) = &self.range {
+            constraints.push(range.to_string());
+        }
+        match &self.length {
+            Some(Range {
+                min: Some(min),
+                max: Some(max),
+                exclusive_max: false,
+            }) if min == max => constraints.push(format!("len={min}")),
+            Some(length) => constraints.push(format!("len={length}")),
+            None => {}
+        }
+        if let Some(pattern) = &self.pattern {
+            constraints.push(format!("pattern={}", quote(pattern.as_str())));
+        }
+        write!(f, "({})", constraints.join(", "))
+    }
+}
+
+impl<T: Display> Display for Range<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(min) = &self.min {
+            write!(f, "{min}")?;
+        }
+        f.write_str(if self.exclusive_max { ".." } else { "..=" })?;
+        match &self.max {
+            Some(max) => write!(f, "{max}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The value as IDL source, text quoted.
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::Decimal(value) => f.write_str(value),
+            Self::String(value) => f.write_str(&quote(value)),
+        }
+    }
+}
+
+/// `@tag(billing, "public api")`, arguments are quoted only if they have to.
+impl Display for Annotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|v| if is_bare(v) { v.node.clone() } else { quote(v) })
+            .collect();
+        write!(f, "({})", args.join(", "))
+    }
+}
+
+/// `404: NotFound as xml`
+impl Display for StatusResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.response_type.node)?;
+        write_media_type(f, &self.media_type)
+    }
+}
+
+/// Writes `endpoint` with `base` left out of its path, it belongs to the
+/// enclosing service. A path not starting with `base` is written in full.
+fn write_endpoint(
+    out: &mut impl Write,
+    endpoint: &Endpoint,
+    base: &[Spanned<Path>],
+) -> fmt::Result {
+    write_docs(out, &endpoint.docs)?;
+    for annotation in &endpoint.annotations {
+        writeln!(out, "{}", annotation.node)?;
+    }
+    write!(out, "{} ", endpoint.method.node)?;
+    write_path(
+        out,
+        endpoint.path.strip_prefix(base).unwrap_or(&endpoint.path),
+    )?;
+    for (i, param) in endpoint.query_params.iter().enumerate() {
+        write!(out, "{}{}", if i == 0 { "?" } else { "&" }, param.node)?;
+    }
+    if !endpoint.headers.is_empty() {
+        write!(out, " [{}]", join(&endpoint.headers, ", "))?;
+    }
+    if let Some(request_type) = &endpoint.request_type {
+        write!(out, " {}", request_type.node)?;
+        write_media_type(out, &endpoint.request_media_type)?;
+    }
+
+    let mut responses: Vec<String> = endpoint.responses.iter().map(|v| v.to_string()).collect();
+    if let Some(response_type) = &endpoint.response_type {
+        let mut response = response_type.to_string();
+        write_media_type(&mut response, &endpoint.response_media_type)?;
+        responses.push(response);
+    }
+    let headers = (!endpoint.response_headers.is_empty())
+        .then(|| format!("[{}]", join(&endpoint.response_headers, ", ")));
+    match (responses.is_empty(), headers) {
+        (true, None) => Ok(()),
+        (true, Some(headers)) => write!(out, " -> {headers}"),
+        (false, headers) => {
+            write!(out, " -> {}", responses.join(" | "))?;
+            match headers {
+                Some(headers) => write!(out, " {headers}"),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// Writes `service` with `base` left out of its own base, it belongs to the
+/// enclosing service. A base not starting with `base` is written in full.
+fn write_service(out: &mut impl Write, service: &Service, base: &[Spanned<Path>]) -> fmt::Result {
+    write_docs(out, &service.docs)?;
+    write!(out, "service {} ", service.name.node)?;
+    match service.base.strip_prefix(base).unwrap_or(&service.base) {
+        [] => out.write_str("/")?,
+        own => write_path(out, own)?,
+    }
+
+    let mut items = Vec::new();
+    let mut endpoints = String::new();
+    for endpoint in &service.endpoints {
+        write_endpoint(&mut endpoints, endpoint, &service.base)?;
+        endpoints.push('\n');
+    }
+    if !endpoints.is_empty() {
+        items.push(endpoints);
+    }
+    for nested in &service.services {
+        let mut text = String::new();
+        write_service(&mut text, nested, &service.base)?;
+        text.push('\n');
+        items.push(text);
+    }
+    if items.is_empty() {
+        out.write_str(" {}")
+    } else {
+        write!(out, " {{\n{}}}", indent(&items.join("\n")))
+    }
+}
+
+fn write_path(out: &mut impl Write, path: &[Spanned<Path>]) -> fmt::Result {
+    path.iter().try_for_each(|v| write!(out, "{}", v.node))
+}
+
+fn write_docs(out: &mut impl Write, docs: &Option<String>) -> fmt::Result {
+    let Some(docs) = docs else {
+        return Ok(());
+    };
+    for line in docs.split('\n') {
+        if line.is_empty() {
+            writeln!(out, "///")?;
+        } else {
+            writeln!(out, "/// {line}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_media_type(out: &mut impl Write, media_type: &Option<Spanned<MediaType>>) -> fmt::Result {
+    match media_type {
+        Some(media_type) => {
+            let name = media_type.shorthand().unwrap_or(media_type.as_str());
+            write!(out, " as {name}")
+        }
+        None => Ok(()),
+    }
+}
+
+/// ` { ... }` with one item per line, or ` {}`.
+fn write_block(out: &mut impl Write, items: impl Iterator<Item = String>) -> fmt::Result {
+    let items: Vec<String> = items.map(|v| format!("{INDENT}{v}\n")).collect();
+    if items.is_empty() {
+        out.write_str(" {}")
+    } else {
+        write!(out, " {{\n{}}}", items.concat())
+    }
+}
+
+fn join<T: Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|v| {
+            if v.is_empty() {
+                "\n".to_owned()
+            } else {
+                format!("{INDENT}{v}\n")
+            }
+        })
+        .collect()
+}
+
+/// Whether an annotation argument can be written without quotes.
+fn is_bare(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-+._:~/=@%".contains(c))
+}
+
+/// `text` as a string literal, the inverse of `literal::unescape`.
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
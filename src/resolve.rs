@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use crate::{
+    Document, Endpoint, EnumDef, Literal, ParseError, Service, Spanned, TypeDef, TypeExpr,
+    TypeName, Variable, VariableType,
+};
+
+impl Document {
+    pub fn type_def(&self, name: &str) -> Option<&TypeDef> {
+        self.types.iter().find(|v| *v.name == name)
+    }
+
+    pub fn enum_def(&self, name: &str) -> Option<&EnumDef> {
+        self.enums.iter().find(|v| *v.name == name)
+    }
+
+    /// The enum whose values `variable` takes, if it has an enum type.
+    pub fn enum_of(&self, variable: &Variable) -> Option<&EnumDef> {
+        match &*variable.variable_type {
+            VariableType::Enum(name) => self.enum_def(name),
+            _ => None,
+        }
+    }
+
+    /// The declaration of the request type of `endpoint`, if it names one.
+    pub fn request_def(&self, endpoint: &Endpoint) -> Option<&TypeDef> {
+        endpoint
+            .request_type
+            .as_ref()
+            .and_then(|v| self.named_def(v))
+    }
+
+    /// The declaration of the response type of `endpoint`, if it names one.
+    pub fn response_def(&self, endpoint: &Endpoint) -> Option<&TypeDef> {
+        endpoint
+            .response_type
+            .as_ref()
+            .and_then(|v| self.named_def(v))
+    }
+
+    fn named_def(&self, type_expr: &TypeExpr) -> Option<&TypeDef> {
+        match type_expr {
+            TypeExpr::Named(name, _) => self.type_def(name),
+            _ => None,
+        }
+    }
+
+    /// Checks that every type and enum is declared once and not under the
+    /// name of a scalar or container, every service among its siblings is
+    /// declared once, no endpoint has two variables or two headers of the
+    /// same name, every type name used by an endpoint or field refers to a
+    /// declaration and gets as many type arguments as it declares, and every
+    /// enum typed variable refers to an enum and defaults to one of its
+    /// values.
+    pub fn resolve(&self) -> Result<(), Vec<ParseError>> {
+        self.resolve_with(&[])
+    }
+
+    /// Like [`Document::resolve`], for a document whose names may also
+    /// refer to the declarations of the documents it imports.
+    pub fn resolve_with(&self, imports: &[&Document]) -> Result<(), Vec<ParseError>> {
+        let scope = Scope {
+            document: self,
+            imports,
+        };
+        let mut errors = Vec::new();
+
+        let mut declared = HashSet::new();
+        let names = self
+            .types
+            .iter()
+            .map(|v| &v.name)
+            .chain(self.enums.iter().map(|v| &v.name));
+        for name in names {
+            // `int` or `List` in a type expression is always the built-in
+            let container = TypeExpr::CONTAINERS
+                .iter()
+                .any(|(v, _)| *v == name.as_str());
+            if VariableType::NAMES.contains(&name.as_str()) || container {
+                errors.push(ParseError::ReservedName {
+                    name: name.node.clone(),
+                    span: name.span,
+                });
+            }
+            let imported = imports
+                .iter()
+                .any(|v| v.type_def(name).is_some() || v.enum_def(name).is_some());
+            if !declared.insert(name.as_str()) || imported {
+                errors.push(duplicate(name));
+            }
+        }
+
+        for type_def in &self.types {
+            let mut params = HashSet::new();
+            for param in &type_def.params {
+                if !params.insert(param.as_str()) {
+                    errors.push(duplicate(param));
+                }
+            }
+            let mut fields = HashSet::new();
+            for field in &type_def.fields {
+                if !fields.insert(field.name.as_str()) {
+                    errors.push(duplicate(&Spanned::new(field.name.clone(), field.span)));
+                }
+                scope.check_type_expr(&field.field_type, &type_def.params, &mut errors);
+            }
+        }
+
+        for enum_def in &self.enums {
+            let mut values = HashSet::new();
+            for value in &enum_def.values {
+                if !values.insert(value.as_str()) {
+                    errors.push(duplicate(value));
+                }
+            }
+        }
+
+        let mut services: Vec<&[Service]> = vec![&self.services];
+        while let Some(siblings) = services.pop() {
+            let mut names = HashSet::new();
+            for service in siblings {
+                if !names.insert(service.name.as_str()) {
+                    errors.push(duplicate(&service.name));
+                }
+                services.push(&service.services);
+            }
+        }
+
+        for endpoint in self.all_endpoints() {
+            // a service base and the path below it may each bring a variable
+            let mut variables = HashSet::new();
+            let path_variables = endpoint
+                .path
+                .iter()
+                .flat_map(|v| v.variables().map(|variable| (variable, v.span)));
+            let query_params = endpoint.query_params.iter().map(|v| (&v.node, v.span));
+            for (variable, span) in path_variables.chain(query_params) {
+                if !variables.insert(variable.name.as_str()) {
+                    errors.push(duplicate(&Spanned::new(variable.name.clone(), span)));
+                }
+            }
+            // header names are case-insensitive
+            for headers in [&endpoint.headers, &endpoint.response_headers] {
+                let mut names = HashSet::new();
+                for header in headers {
+                    if !names.insert(header.name.to_ascii_lowercase()) {
+                        errors.push(duplicate(&Spanned::new(header.name.clone(), header.span)));
+                    }
+                }
+            }
+
+            let path_variables = endpoint.path.iter().flat_map(|v| v.variables());
+            let params = endpoint
+                .query_params
+                .iter()
+                .chain(&endpoint.headers)
+                .chain(&endpoint.response_headers)
+                .map(|v| &v.node);
+            for variable in path_variables.chain(params) {
+                scope.check_variable(variable, &mut errors);
+            }
+
+            let statuses = endpoint.responses.iter().map(|v| &v.response_type);
+            for type_expr in [&endpoint.request_type, &endpoint.response_type]
+                .into_iter()
+                .flatten()
+                .chain(statuses)
+            {
+                scope.check_type_expr(type_expr, &[], &mut errors);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The declarations a document can refer to: its own, then those of its
+/// imports.
+struct Scope<'a> {
+    document: &'a Document,
+    imports: &'a [&'a Document],
+}
+
+impl<'a> Scope<'a> {
+    fn documents(&self) -> impl Iterator<Item = &'a Document> + '_ {
+        std::iter::once(self.document).chain(self.imports.iter().copied())
+    }
+
+    fn type_def(&self, name: &str) -> Option<&'a TypeDef> {
+        self.documents().find_map(|v| v.type_def(name))
+    }
+
+    fn enum_def(&self, name: &str) -> Option<&'a EnumDef> {
+        self.documents().find_map(|v| v.enum_def(name))
+    }
+
+    /// Checks the names in `type_expr`, which may also refer to the type
+    /// parameters `params` of the declaration it is used in.
+    fn check_type_expr(
+        &self,
+        type_expr: &Spanned<TypeExpr>,
+        params: &[Spanned<TypeName>],
+        errors: &mut Vec<ParseError>,
+    ) {
+        let (name, args) = match &type_expr.node {
+            TypeExpr::Scalar(_) => return,
+            TypeExpr::List(inner) | TypeExpr::Option(inner) => {
+                return self.check_type_expr(inner, params, errors)
+            }
+            TypeExpr::Map(key, value) => {
+                self.check_type_expr(key, params, errors);
+                return self.check_type_expr(value, params, errors);
+            }
+            TypeExpr::Named(name, args) => (name, args),
+        };
+        for arg in args {
+            self.check_type_expr(arg, params, errors);
+        }
+
+        let expected = if params.iter().any(|v| **v == *name) {
+            0
+        } else if let Some(type_def) = self.type_def(name) {
+            type_def.params.len()
+        } else if self.enum_def(name).is_some() {
+            0
+        } else {
+            errors.push(ParseError::UndeclaredType {
+                name: name.clone(),
+                span: type_expr.span,
+            });
+            return;
+        };
+        if args.len() != expected {
+            errors.push(ParseError::TypeArity {
+                name: name.clone(),
+                expected,
+                found: args.len(),
+                span: type_expr.span,
+            });
+        }
+    }
+
+    fn check_variable(&self, variable: &Variable, errors: &mut Vec<ParseError>) {
+        let VariableType::Enum(name) = &*variable.variable_type else {
+            return;
+        };
+        let span = variable.variable_type.span;
+        match self.enum_def(name) {
+            Some(enum_def) => {
+                if let Some(Spanned {
+                    node: Literal::String(value),
+                    span,
+                }) = &variable.default
+                {
+                    if !enum_def.contains(value) {
+                        errors.push(ParseError::InvalidDefault {
+                            value: value.clone(),
+                            expected: name.clone(),
+                            span: *span,
+                        });
+                    }
+                }
+            }
+            None if self.type_def(name).is_some() => errors.push(ParseError::NotAnEnum {
+                name: name.clone(),
+                span,
+            }),
+            None => errors.push(ParseError::UnsupportType {
+                name: name.clone(),
+                span,
+            }),
+        }
+    }
+}
+
+fn duplicate(name: &Spanned<String>) -> ParseError {
+    ParseError::Duplicate {
+        name: name.node.clone(),
+        span: name.span,
+    }
+}
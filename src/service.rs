@@ -0,0 +1,151 @@
+use pest::iterators::Pair;
+
+use crate::{
+    join_docs, path_of, Children, Document, Endpoint, EndpointParser, ParseError, Path, Rule,
+    Spanned,
+};
+
+/// `service Billing /api/v2/billing { GET /invoices -> InvoiceList }`
+///
+/// Paths are stored in full: `base` includes the bases of the enclosing
+/// services, and the path of every endpoint starts with `base`.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Service {
+    /// The `///` lines above the service.
+    pub docs: Option<String>,
+    pub name: Spanned<String>,
+    pub base: Vec<Spanned<Path>>,
+    pub endpoints: Vec<Endpoint>,
+    pub services: Vec<Service>,
+}
+
+impl Service {
+    /// The endpoints of this service and of the services nested in it.
+    pub fn all_endpoints(&self) -> Box<dyn Iterator<Item = &Endpoint> + '_> {
+        Box::new(
+            self.endpoints
+                .iter()
+                .chain(self.services.iter().flat_map(|v| v.all_endpoints())),
+        )
+    }
+
+    /// Puts `base` in front of every path in this service. A trailing slash
+    /// of `base` is dropped, `/api/` and `/api` prefix paths alike.
+    fn prefix(&mut self, base: &[Spanned<Path>]) {
+        let base = match base.split_last() {
+            Some((last, rest)) if last.node == Path::TrailingSlash => rest,
+            _ => base,
+        };
+        self.base.splice(0..0, base.iter().cloned());
+        for endpoint in &mut self.endpoints {
+            endpoint.path.splice(0..0, base.iter().cloned());
+        }
+        for service in &mut self.services {
+            service.prefix(base);
+        }
+    }
+}
+
+impl Document {
+    /// Every endpoint, the top level ones first, then those of each service.
+    pub fn all_endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        self.endpoints
+            .iter()
+            .chain(self.services.iter().flat_map(|v| v.all_endpoints()))
+    }
+
+    /// Looks up a service by name, nested services included.
+    pub fn service(&self, name: &str) -> Option<&Service> {
+        let mut services: Vec<&Service> = self.services.iter().collect();
+        while let Some(service) = services.pop() {
+            if *service.name == name {
+                return Some(service);
+            }
+            services.extend(&service.services);
+        }
+        None
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for Service {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::service == value.as_rule() {
+            Self::build(value, None)
+        } else {
+            Err(ParseError::unexpect_rule(Rule::service, &value))
+        }
+    }
+}
+
+impl Service {
+    /// Builds the service of a `recovering_service` pair. Endpoints and
+    /// nested services that do not parse are left out, their errors are
+    /// pushed to `errors`.
+    pub(crate) fn recover(
+        value: Pair<'_, Rule>,
+        input: &str,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<Self, ParseError> {
+        Self::build(value, Some((input, errors)))
+    }
+
+    /// Builds a service, an item of it that fails fails the service unless
+    /// there is somewhere to put its error, along with the input to
+    /// diagnose broken items in.
+    fn build(
+        value: Pair<'_, Rule>,
+        mut recovered: Option<(&str, &mut Vec<ParseError>)>,
+    ) -> Result<Self, ParseError> {
+        let mut pairs = Children::of(value);
+        let mut docs = Vec::new();
+        let name = pairs.next_documented(&mut docs)?;
+        let base = path_of(pairs.next_pair()?)?;
+        if let Some(last) = base.last() {
+            if let Some(variable) = last.catch_all() {
+                return Err(ParseError::InvalidCatchAll {
+                    name: variable.name.clone(),
+                    reason: "a service base cannot end in one".to_owned(),
+                    span: last.span,
+                });
+            }
+        }
+
+        let mut endpoints = Vec::new();
+        let mut services = Vec::new();
+        for pair in pairs {
+            let result = match (pair.as_rule(), &mut recovered) {
+                (Rule::invalid_member, Some((input, errors))) => {
+                    errors.extend(EndpointParser::diagnose(input, pair));
+                    Ok(())
+                }
+                (Rule::invalid_member, None) => {
+                    Err(ParseError::unexpect_rule(Rule::endpoint, &pair))
+                }
+                (Rule::service | Rule::recovering_service, _) => {
+                    let recovered = recovered
+                        .as_mut()
+                        .map(|(input, errors)| (*input, &mut **errors));
+                    Self::build(pair, recovered).map(|v| services.push(v))
+                }
+                _ => Endpoint::try_from(pair).map(|v| endpoints.push(v)),
+            };
+            match (result, &mut recovered) {
+                (Err(err), Some((_, errors))) => errors.push(err),
+                (Err(err), None) => return Err(err),
+                (Ok(()), _) => {}
+            }
+        }
+
+        let mut service = Self {
+            docs: join_docs(&docs),
+            name: Spanned::new(name.as_str().to_owned(), name.as_span().into()),
+            base: Vec::new(),
+            endpoints,
+            services,
+        };
+        service.prefix(&base);
+        Ok(service)
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use crate::FileError;
+
+/// Identifies a file in a [`SourceMap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// Every file a [`crate::Loader`] read, so spans and errors found in a file
+/// can be traced back to its path and text.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) -> FileId {
+        self.files.push(SourceFile {
+            path: path.into(),
+            source: source.into(),
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    /// # Panics
+    ///
+    /// If `file` was not added to this map.
+    pub fn get(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0]
+    }
+
+    pub fn path(&self, file: FileId) -> &Path {
+        &self.get(file).path
+    }
+
+    pub fn source(&self, file: FileId) -> &str {
+        &self.get(file).source
+    }
+
+    pub fn find(&self, path: &Path) -> Option<FileId> {
+        self.files.iter().position(|v| v.path == path).map(FileId)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files.iter().enumerate().map(|(i, v)| (FileId(i), v))
+    }
+
+    /// Renders `err` like [`crate::ParseError::render`], with the path and
+    /// position of the error below the message.
+    ///
+    /// ```text
+    /// error: undeclared type `Usr` at 2:11
+    ///  --> api/users.idl:2:11
+    ///   |
+    /// 2 | GET /a -> Usr
+    ///   |           ^^^
+    /// ```
+    pub fn render(&self, err: &FileError) -> String {
+        let rendered = err.error.render(self.source(err.file));
+        let (message, snippet) = rendered.split_once('\n').unwrap_or((&rendered, ""));
+        format!(
+            "{message}\n --> {}:{}\n{snippet}",
+            self.path(err.file).display(),
+            err.error.span()
+        )
+    }
+}
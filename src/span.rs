@@ -0,0 +1,102 @@
+use std::{fmt, ops::Deref};
+
+/// Location of a node in the parsed input.
+///
+/// `start` and `end` are byte offsets, `line` and `col` are the 1-based
+/// position of `start`. Nodes built by hand carry `Span::default()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Whitespace an endpoint rule skipped after its last token is not part of
+/// the span.
+impl From<pest::Span<'_>> for Span {
+    fn from(value: pest::Span<'_>) -> Self {
+        let (line, col) = value.start_pos().line_col();
+        Self {
+            start: value.start(),
+            end: value.start() + value.as_str().trim_end().len(),
+            line,
+            col,
+        }
+    }
+}
+
+impl Span {
+    /// Moves a span found in `source[by..]` so it points into `source`.
+    pub(crate) fn offset(self, source: &str, by: usize) -> Self {
+        let start = self.start + by;
+        let (line, col) = pest::Position::new(source, start).map_or((0, 0), |v| v.line_col());
+        Self {
+            start,
+            end: self.end + by,
+            line,
+            col,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// An AST node together with the span it was parsed from.
+///
+/// Comparison only looks at the node, so ASTs parsed from differently
+/// formatted input are still equal.
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Spanned<U> {
+        Spanned {
+            node: f(self.node),
+            span: self.span,
+        }
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Self {
+        Self::new(node, Span::default())
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Spanned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Spanned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.node.partial_cmp(&other.node)
+    }
+}
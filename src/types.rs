@@ -0,0 +1,220 @@
+use pest::iterators::Pair;
+
+use crate::{join_docs, spanned, Children, ParseError, Rule, Spanned, TypeName, VariableType};
+
+/// `type User { id: long, name: string, email?: string }`, or a generic
+/// `type Page<T> { items: List<T>, total: long }`.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct TypeDef {
+    /// The `///` lines above the declaration.
+    pub docs: Option<String>,
+    pub name: Spanned<TypeName>,
+    pub params: Vec<Spanned<TypeName>>,
+    pub fields: Vec<Spanned<Field>>,
+}
+
+impl TypeDef {
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().map(|v| &v.node).find(|v| v.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Field {
+    pub name: String,
+    pub field_type: Spanned<TypeExpr>,
+    /// `name?: type`, the field may be left out.
+    pub optional: bool,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, field_type: TypeExpr) -> Self {
+        Self {
+            name: name.into(),
+            field_type: field_type.into(),
+            optional: false,
+        }
+    }
+
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+/// `enum Order { asc, desc }`
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct EnumDef {
+    /// The `///` lines above the declaration.
+    pub docs: Option<String>,
+    pub name: Spanned<TypeName>,
+    pub values: Vec<Spanned<String>>,
+}
+
+impl EnumDef {
+    /// The allowed values, in declaration order.
+    pub fn values(&self) -> impl Iterator<Item = &str> {
+        self.values.iter().map(|v| v.as_str())
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.values().any(|v| v == value)
+    }
+}
+
+/// A type as written in a declaration or an endpoint signature.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum TypeExpr {
+    Scalar(VariableType),
+    /// `List<T>`
+    List(Box<Spanned<TypeExpr>>),
+    /// `Map<K, V>`
+    Map(Box<Spanned<TypeExpr>>, Box<Spanned<TypeExpr>>),
+    /// `Option<T>`
+    Option(Box<Spanned<TypeExpr>>),
+    /// A type or enum declared elsewhere in the document, or a parameter of
+    /// the generic type it is used in. `args` is empty unless the declared
+    /// type is generic, e.g. `Page<Order>`.
+    Named(TypeName, Vec<Spanned<TypeExpr>>),
+}
+
+impl TypeExpr {
+    /// The built-in generic containers and the number of arguments they take.
+    pub const CONTAINERS: &'static [(&'static str, usize)] =
+        &[("List", 1), ("Map", 2), ("Option", 1)];
+
+    pub fn named(name: impl Into<TypeName>) -> Self {
+        Self::Named(name.into(), Vec::new())
+    }
+
+    pub fn generic(name: impl Into<TypeName>, args: Vec<TypeExpr>) -> Self {
+        Self::Named(name.into(), args.into_iter().map(Into::into).collect())
+    }
+
+    pub fn list(item: TypeExpr) -> Self {
+        Self::List(Box::new(item.into()))
+    }
+
+    pub fn map(key: TypeExpr, value: TypeExpr) -> Self {
+        Self::Map(Box::new(key.into()), Box::new(value.into()))
+    }
+
+    pub fn option(inner: TypeExpr) -> Self {
+        Self::Option(Box::new(inner.into()))
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for TypeDef {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::type_def == value.as_rule() {
+            let mut pairs = Children::of(value);
+            let mut docs = Vec::new();
+            let name = pairs.next_documented(&mut docs)?;
+            let mut params = Vec::new();
+            let mut fields = Vec::new();
+            for pair in pairs {
+                match pair.as_rule() {
+                    Rule::type_params => params.extend(
+                        pair.into_inner()
+                            .map(|v| Spanned::new(v.as_str().to_owned(), v.as_span().into())),
+                    ),
+                    _ => fields.push(spanned(pair)?),
+                }
+            }
+
+            Ok(Self {
+                docs: join_docs(&docs),
+                name: Spanned::new(name.as_str().to_owned(), name.as_span().into()),
+                params,
+                fields,
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::type_def, &value))
+        }
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for EnumDef {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::enum_def == value.as_rule() {
+            let mut pairs = Children::of(value);
+            let mut docs = Vec::new();
+            let name = pairs.next_documented(&mut docs)?;
+            let values = pairs
+                .map(|v| Spanned::new(v.as_str().to_owned(), v.as_span().into()))
+                .collect();
+
+            Ok(Self {
+                docs: join_docs(&docs),
+                name: Spanned::new(name.as_str().to_owned(), name.as_span().into()),
+                values,
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::enum_def, &value))
+        }
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for Field {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::field == value.as_rule() {
+            let mut pairs = Children::of(value);
+            let name = pairs.next_pair()?;
+            let mut pair = pairs.next_pair()?;
+            let optional = pair.as_rule() == Rule::optional;
+            if optional {
+                pair = pairs.next_pair()?;
+            }
+
+            Ok(Self {
+                name: name.as_str().to_owned(),
+                field_type: spanned(pair)?,
+                optional,
+            })
+        } else {
+            Err(ParseError::unexpect_rule(Rule::field, &value))
+        }
+    }
+}
+
+impl TryFrom<Pair<'_, Rule>> for TypeExpr {
+    type Error = ParseError;
+
+    fn try_from(value: Pair<'_, Rule>) -> Result<Self, Self::Error> {
+        if Rule::type_expr == value.as_rule() {
+            let span = value.as_span().into();
+            let mut pairs = Children::of(value);
+            let pair = pairs.next_pair()?;
+            if pair.as_rule() == Rule::variable_type {
+                return Ok(Self::Scalar(pair.try_into()?));
+            }
+            let name = pair.as_str().to_owned();
+            let args = pairs.map(spanned).collect::<Result<Vec<_>, _>>()?;
+
+            let Some(&(_, arity)) = Self::CONTAINERS.iter().find(|(v, _)| *v == name) else {
+                return Ok(Self::Named(name, args));
+            };
+            let found = args.len();
+            let mut args = args.into_iter().map(Box::new);
+            match (name.as_str(), args.next(), args.next(), args.next()) {
+                ("List", Some(item), None, _) => Ok(Self::List(item)),
+                ("Map", Some(key), Some(value), None) => Ok(Self::Map(key, value)),
+                ("Option", Some(inner), None, _) => Ok(Self::Option(inner)),
+                _ => Err(ParseError::TypeArity {
+                    name,
+                    expected: arity,
+                    found,
+                    span,
+                }),
+            }
+        } else {
+            Err(ParseError::unexpect_rule(Rule::type_expr, &value))
+        }
+    }
+}